@@ -0,0 +1,44 @@
+//! Records what every channel last sent, so the editor can show which channels are actually
+//! reaching the network.
+
+use nannou_osc as osc;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use crate::NUM_CHANNELS;
+
+/// Written by the task executor whenever a channel sends something.
+pub struct ChannelActivity {
+    last_sent: Mutex<Vec<Option<Sent>>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Sent {
+    /// The value as it was encoded in the message.
+    pub value: osc::Type,
+    pub time: Instant,
+}
+
+impl Default for ChannelActivity {
+    fn default() -> Self {
+        Self {
+            last_sent: Mutex::new(vec![None; NUM_CHANNELS]),
+        }
+    }
+}
+
+impl ChannelActivity {
+    pub fn record(&self, index: usize, value: osc::Type) {
+        if let Some(last_sent) = self.last_sent.lock().unwrap().get_mut(index) {
+            *last_sent = Some(Sent {
+                value,
+                time: Instant::now(),
+            });
+        }
+    }
+
+    /// What each channel sent last, if it has sent anything yet.
+    pub fn last_sent(&self) -> Vec<Option<Sent>> {
+        self.last_sent.lock().unwrap().clone()
+    }
+}
@@ -0,0 +1,154 @@
+//! Restricts which hosts may send OSC to the plugin, so strangers on a shared venue network can't
+//! take over the channels. Entries are single addresses like `10.0.0.5` or CIDR ranges like
+//! `192.168.1.0/24` and `fd00::/8`.
+
+use nih_plug::prelude::*;
+use std::net::IpAddr;
+
+/// A parsed allowlist. An empty allowlist allows everything.
+#[derive(Debug, Default)]
+pub struct Allowlist {
+    /// The entries this was parsed from, to tell when the setting has changed.
+    entries: Vec<String>,
+    ranges: Vec<IpRange>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct IpRange {
+    network: IpAddr,
+    prefix_len: u8,
+}
+
+impl Allowlist {
+    /// Parse the allowlist setting, skipping and logging invalid entries.
+    pub fn parse(entries: &[String]) -> Self {
+        let ranges = entries
+            .iter()
+            .filter_map(|entry| {
+                let range = IpRange::parse(entry.trim());
+                if range.is_none() {
+                    nih_warn!("Ignoring invalid allowlist entry {entry:?}");
+                }
+                range
+            })
+            .collect();
+
+        Self {
+            entries: entries.to_vec(),
+            ranges,
+        }
+    }
+
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    pub fn allows(&self, addr: IpAddr) -> bool {
+        self.entries.is_empty() || self.ranges.iter().any(|range| range.contains(addr))
+    }
+}
+
+impl IpRange {
+    fn parse(entry: &str) -> Option<Self> {
+        let (network, prefix_len) = match entry.split_once('/') {
+            Some((network, prefix_len)) => (network.parse().ok()?, Some(prefix_len.parse().ok()?)),
+            None => (entry.parse().ok()?, None),
+        };
+        let max_prefix_len = match network {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        let prefix_len = prefix_len.unwrap_or(max_prefix_len);
+
+        (prefix_len <= max_prefix_len).then_some(Self {
+            network,
+            prefix_len,
+        })
+    }
+
+    fn contains(&self, addr: IpAddr) -> bool {
+        // IPv4 senders may show up as IPv4-mapped IPv6 addresses
+        let addr = match addr {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(addr, IpAddr::V4),
+            addr => addr,
+        };
+
+        match (self.network, addr) {
+            (IpAddr::V4(network), IpAddr::V4(addr)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - self.prefix_len as u32)
+                    .unwrap_or(0);
+                u32::from(network) & mask == u32::from(addr) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(addr)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - self.prefix_len as u32)
+                    .unwrap_or(0);
+                u128::from(network) & mask == u128::from(addr) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allowlist(entries: &[&str]) -> Allowlist {
+        let entries: Vec<String> = entries.iter().map(|entry| entry.to_string()).collect();
+        Allowlist::parse(&entries)
+    }
+
+    fn ip(addr: &str) -> IpAddr {
+        addr.parse().unwrap()
+    }
+
+    #[test]
+    fn empty_allowlist_allows_everyone() {
+        let allowlist = allowlist(&[]);
+        assert!(allowlist.allows(ip("10.0.0.5")));
+        assert!(allowlist.allows(ip("::1")));
+    }
+
+    #[test]
+    fn single_addresses() {
+        let allowlist = allowlist(&["10.0.0.5", " ::1 "]);
+        assert!(allowlist.allows(ip("10.0.0.5")));
+        assert!(!allowlist.allows(ip("10.0.0.6")));
+        assert!(allowlist.allows(ip("::1")));
+        assert!(!allowlist.allows(ip("::2")));
+    }
+
+    #[test]
+    fn cidr_ranges() {
+        let allowlist = allowlist(&["192.168.1.0/24", "fd00::/8"]);
+        assert!(allowlist.allows(ip("192.168.1.0")));
+        assert!(allowlist.allows(ip("192.168.1.255")));
+        assert!(!allowlist.allows(ip("192.168.2.1")));
+        assert!(allowlist.allows(ip("fd12:3456::1")));
+        assert!(!allowlist.allows(ip("fe80::1")));
+    }
+
+    #[test]
+    fn zero_length_prefixes_match_their_whole_family() {
+        let allowlist = allowlist(&["0.0.0.0/0"]);
+        assert!(allowlist.allows(ip("203.0.113.7")));
+        assert!(!allowlist.allows(ip("2001:db8::1")));
+    }
+
+    #[test]
+    fn ipv4_mapped_senders_match_ipv4_entries() {
+        let allowlist = allowlist(&["10.0.0.0/8"]);
+        assert!(allowlist.allows(ip("::ffff:10.1.2.3")));
+        assert!(!allowlist.allows(ip("::ffff:11.1.2.3")));
+    }
+
+    #[test]
+    fn invalid_entries_are_skipped() {
+        let allowlist = allowlist(&["10.0.0.0/33", "not an address", "10.0.0.5"]);
+        assert_eq!(allowlist.ranges.len(), 1);
+        assert!(allowlist.allows(ip("10.0.0.5")));
+        assert!(!allowlist.allows(ip("10.0.0.6")));
+    }
+}
@@ -0,0 +1,570 @@
+//! Audio input analysis that's too expensive for the audio thread. The audio thread only copies
+//! the input into a ring buffer, and the analysis runs on its own thread. Since only the audio
+//! thread can queue background tasks, the analysis thread stores its results here and flags them
+//! as ready, after which the audio thread queues a task that sends them.
+//!
+//! - `/spectrum <band 1> <band 2> ...` with the amplitude of a configurable number of
+//!   logarithmically spaced frequency bands. A full scale sine wave results in an amplitude of
+//!   about one in its band.
+//! - `/pitch/hz` and `/pitch/confidence`, see the `pitch` module.
+//! - `/onset <strength>`, see the `onset` module.
+//! - `/spectral/centroid <Hz>`, the spectrum's center of mass, which follows how bright the input
+//!   sounds.
+//! - `/spectral/flatness <flatness>` between zero for pure tones and one for white noise.
+//! - `/spectral/rolloff <Hz>`, the frequency below which 85% of the energy lies.
+//! - `/waveform <blob>` with a downsampled chunk of the input's waveform, for oscilloscope-style
+//!   visuals. The blob holds a configurable number of big-endian 32-bit floats, each the average of
+//!   the samples it covers, and consecutive blobs continue where the previous one left off.
+
+use nannou_osc as osc;
+use nih_plug::prelude::*;
+use rtrb::{Consumer, Producer, RingBuffer};
+use rustfft::num_complex::Complex;
+use rustfft::{Fft, FftPlanner};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::onset::OnsetDetector;
+use crate::pitch::{Pitch, PitchTracker};
+use crate::SpaceRadioParams;
+
+/// The most input channels that are analyzed. Channels past this are ignored.
+pub const MAX_CHANNELS: usize = 8;
+/// The number of samples analyzed at once.
+const FRAME_SIZE: usize = 2048;
+/// The number of new samples between two analysis frames.
+const HOP_SIZE: usize = 512;
+/// How much input the ring buffer holds. When the analysis thread falls behind this far, input is
+/// dropped.
+const RING_BUFFER_SIZE: usize = FRAME_SIZE * 8;
+/// How long the analysis thread sleeps when it has caught up with the input.
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+/// The lowest frequency that's included in the spectrum's bands.
+const MIN_FREQUENCY: f32 = 20.0;
+/// The share of the energy that lies below the spectral rolloff.
+const ROLLOFF_ENERGY: f32 = 0.85;
+
+/// Which input the levels, envelopes, input gate, and analyses listen to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AnalysisSource {
+    /// The track the plugin sits on.
+    #[default]
+    Main,
+    /// The sidechain input, for instance to analyze the drum bus while the plugin lives on the
+    /// master. Falls back to the main input if the host doesn't provide one.
+    Sidechain,
+}
+
+impl AnalysisSource {
+    /// The buffer to analyze for this source.
+    pub fn select<'a>(self, main: &'a Buffer<'a>, aux: &'a AuxiliaryBuffers<'_>) -> &'a Buffer<'a> {
+        match (self, aux.inputs.first()) {
+            (AnalysisSource::Sidechain, Some(sidechain))
+                if !sidechain.as_slice_immutable().is_empty() =>
+            {
+                sidechain
+            }
+            _ => main,
+        }
+    }
+}
+
+/// One value per input channel. This is small enough to be sent to the background task as is.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChannelValues {
+    values: [f32; MAX_CHANNELS],
+    len: usize,
+}
+
+impl ChannelValues {
+    pub fn new(values: impl IntoIterator<Item = f32>) -> Self {
+        let mut channel_values = Self {
+            values: [0.0; MAX_CHANNELS],
+            len: 0,
+        };
+        for (value, slot) in values.into_iter().zip(channel_values.values.iter_mut()) {
+            *slot = value;
+            channel_values.len += 1;
+        }
+
+        channel_values
+    }
+
+    pub fn as_slice(&self) -> &[f32] {
+        &self.values[..self.len]
+    }
+
+    /// One float argument per channel.
+    pub fn to_args(self) -> Vec<osc::Type> {
+        self.as_slice()
+            .iter()
+            .map(|&value| osc::Type::Float(value))
+            .collect()
+    }
+}
+
+/// Spectral descriptors of one frame, giving a compact idea of the input's timbre.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpectralFeatures {
+    pub centroid: f32,
+    pub flatness: f32,
+    pub rolloff: f32,
+}
+
+impl SpectralFeatures {
+    pub fn to_osc(self) -> [(&'static str, Vec<osc::Type>); 3] {
+        [
+            ("/spectral/centroid", vec![osc::Type::Float(self.centroid)]),
+            ("/spectral/flatness", vec![osc::Type::Float(self.flatness)]),
+            ("/spectral/rolloff", vec![osc::Type::Float(self.rolloff)]),
+        ]
+    }
+}
+
+/// The analysis results, shared between the analysis thread, the audio thread, and the task
+/// executor.
+#[derive(Default)]
+pub struct AnalysisResults {
+    /// The audio thread's sample rate, as bits.
+    sample_rate: AtomicU32,
+    spectrum_ready: AtomicBool,
+    spectrum: Mutex<Vec<f32>>,
+    pitch_ready: AtomicBool,
+    /// The pitch's frequency and confidence, as bits.
+    pitch_hz: AtomicU32,
+    pitch_confidence: AtomicU32,
+    onset_ready: AtomicBool,
+    /// The onset's strength, as bits.
+    onset_strength: AtomicU32,
+    features_ready: AtomicBool,
+    /// The spectral features, as bits.
+    centroid: AtomicU32,
+    flatness: AtomicU32,
+    rolloff: AtomicU32,
+    waveform_ready: AtomicBool,
+    waveform: Mutex<Vec<f32>>,
+}
+
+impl AnalysisResults {
+    pub fn set_sample_rate(&self, sample_rate: f32) {
+        self.sample_rate
+            .store(sample_rate.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Whether a new spectrum has been computed since this was last called.
+    pub fn take_spectrum_ready(&self) -> bool {
+        self.spectrum_ready.swap(false, Ordering::Relaxed)
+    }
+
+    pub fn spectrum_args(&self) -> Vec<osc::Type> {
+        self.spectrum
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|&band| osc::Type::Float(band))
+            .collect()
+    }
+
+    /// The pitch, if it's been tracked again since this was last called.
+    pub fn take_pitch(&self) -> Option<Pitch> {
+        self.pitch_ready
+            .swap(false, Ordering::Acquire)
+            .then(|| Pitch {
+                hz: f32::from_bits(self.pitch_hz.load(Ordering::Relaxed)),
+                confidence: f32::from_bits(self.pitch_confidence.load(Ordering::Relaxed)),
+            })
+    }
+
+    fn store_pitch(&self, pitch: Pitch) {
+        self.pitch_hz.store(pitch.hz.to_bits(), Ordering::Relaxed);
+        self.pitch_confidence
+            .store(pitch.confidence.to_bits(), Ordering::Relaxed);
+        self.pitch_ready.store(true, Ordering::Release);
+    }
+
+    /// The strength of the onset that was detected since this was last called, if any.
+    pub fn take_onset(&self) -> Option<f32> {
+        self.onset_ready
+            .swap(false, Ordering::Acquire)
+            .then(|| f32::from_bits(self.onset_strength.load(Ordering::Relaxed)))
+    }
+
+    fn store_onset(&self, strength: f32) {
+        self.onset_strength
+            .store(strength.to_bits(), Ordering::Relaxed);
+        self.onset_ready.store(true, Ordering::Release);
+    }
+
+    /// The spectral features, if they've been computed again since this was last called.
+    pub fn take_features(&self) -> Option<SpectralFeatures> {
+        self.features_ready
+            .swap(false, Ordering::Acquire)
+            .then(|| SpectralFeatures {
+                centroid: f32::from_bits(self.centroid.load(Ordering::Relaxed)),
+                flatness: f32::from_bits(self.flatness.load(Ordering::Relaxed)),
+                rolloff: f32::from_bits(self.rolloff.load(Ordering::Relaxed)),
+            })
+    }
+
+    fn store_features(&self, features: SpectralFeatures) {
+        self.centroid
+            .store(features.centroid.to_bits(), Ordering::Relaxed);
+        self.flatness
+            .store(features.flatness.to_bits(), Ordering::Relaxed);
+        self.rolloff
+            .store(features.rolloff.to_bits(), Ordering::Relaxed);
+        self.features_ready.store(true, Ordering::Release);
+    }
+
+    /// Whether a new waveform chunk has been collected since this was last called.
+    pub fn take_waveform_ready(&self) -> bool {
+        self.waveform_ready.swap(false, Ordering::Relaxed)
+    }
+
+    pub fn waveform_blob(&self) -> Vec<u8> {
+        self.waveform
+            .lock()
+            .unwrap()
+            .iter()
+            .flat_map(|point| point.to_be_bytes())
+            .collect()
+    }
+}
+
+/// Whether any of the analyses is enabled, in which case the audio thread should feed it input.
+/// Settings that are locked at the moment count as disabled.
+pub(crate) fn wants_input(params: &SpaceRadioParams) -> bool {
+    [
+        &params.spectrum_enabled,
+        &params.pitch_enabled,
+        &params.onset_enabled,
+        &params.features_enabled,
+        &params.waveform_enabled,
+    ]
+    .iter()
+    .any(|enabled| enabled.try_read().map_or(false, |on| *on))
+}
+
+/// The audio thread's end of the ring buffer.
+pub struct AnalysisInput {
+    producer: Producer<f32>,
+}
+
+impl AnalysisInput {
+    /// Copy a downmix of the block's input into the ring buffer, without blocking or allocating.
+    pub fn push(&mut self, buffer: &Buffer) {
+        let channels = buffer.as_slice_immutable();
+        if channels.is_empty() {
+            return;
+        }
+
+        let gain = 1.0 / channels.len() as f32;
+        for sample_idx in 0..buffer.samples() {
+            let sample = channels
+                .iter()
+                .map(|channel| channel[sample_idx])
+                .sum::<f32>()
+                * gain;
+            if self.producer.push(sample).is_err() {
+                break;
+            }
+        }
+    }
+}
+
+/// Runs the analysis. Stops and joins its thread when dropped.
+pub struct Analyzer {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Analyzer {
+    pub(crate) fn spawn(
+        params: Arc<SpaceRadioParams>,
+        results: Arc<AnalysisResults>,
+    ) -> (Self, AnalysisInput) {
+        let (producer, consumer) = RingBuffer::new(RING_BUFFER_SIZE);
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = {
+            let stop = Arc::clone(&stop);
+            thread::Builder::new()
+                .name(String::from("space-radio-analysis"))
+                .spawn(move || run(&params, &results, consumer, &stop))
+                .ok()
+        };
+
+        (Self { stop, handle }, AnalysisInput { producer })
+    }
+}
+
+impl Drop for Analyzer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn run(
+    params: &SpaceRadioParams,
+    results: &AnalysisResults,
+    mut consumer: Consumer<f32>,
+    stop: &AtomicBool,
+) {
+    let fft = FftPlanner::new().plan_fft_forward(FRAME_SIZE);
+    let mut frame = Frame::new(fft);
+    let mut pitch_tracker = PitchTracker::default();
+    let mut onset_detector = OnsetDetector::default();
+    let mut window: VecDeque<f32> = VecDeque::from(vec![0.0; FRAME_SIZE]);
+    let mut new_samples = 0;
+    let mut spectrum_schedule = Schedule::default();
+    let mut pitch_schedule = Schedule::default();
+    let mut features_schedule = Schedule::default();
+    let mut waveform = Waveform::default();
+
+    while !stop.load(Ordering::Relaxed) {
+        let Ok(sample) = consumer.pop() else {
+            thread::sleep(POLL_INTERVAL);
+            continue;
+        };
+
+        window.pop_front();
+        window.push_back(sample);
+        waveform.push(sample, results);
+        new_samples += 1;
+        if new_samples < HOP_SIZE {
+            continue;
+        }
+        new_samples = 0;
+
+        let sample_rate = f32::from_bits(results.sample_rate.load(Ordering::Relaxed));
+        waveform.configure(params, sample_rate);
+        let input = window.make_contiguous();
+        let spectrum_due =
+            spectrum_schedule.due(&params.spectrum_enabled, &params.spectrum_interval_ms);
+        let features_due =
+            features_schedule.due(&params.features_enabled, &params.features_interval_ms);
+        // Onsets need to look at every frame to find the peaks
+        let onset_enabled = *params.onset_enabled.read().unwrap();
+        if spectrum_due || features_due || onset_enabled {
+            frame.analyze(input);
+        }
+
+        if spectrum_due {
+            let num_bands = *params.spectrum_bands.read().unwrap() as usize;
+            *results.spectrum.lock().unwrap() = frame.bands(num_bands, sample_rate);
+            results.spectrum_ready.store(true, Ordering::Relaxed);
+        }
+        if features_due {
+            results.store_features(frame.features(sample_rate));
+        }
+        if pitch_schedule.due(&params.pitch_enabled, &params.pitch_interval_ms) {
+            results.store_pitch(pitch_tracker.process(input, sample_rate));
+        }
+        if onset_enabled {
+            let hop = Duration::from_secs_f32(HOP_SIZE as f32 / sample_rate);
+            let sensitivity = *params.onset_sensitivity.read().unwrap();
+            if let Some(strength) = onset_detector.process(frame.magnitudes(), hop, sensitivity) {
+                results.store_onset(strength);
+            }
+        } else {
+            onset_detector.reset();
+        }
+    }
+}
+
+/// Rate limits one of the analyses.
+#[derive(Default)]
+struct Schedule {
+    last_run: Option<Instant>,
+}
+
+impl Schedule {
+    /// Whether the analysis is enabled and its interval has passed. If so, it counts as having run.
+    fn due(&mut self, enabled: &RwLock<bool>, interval_ms: &RwLock<u32>) -> bool {
+        if !*enabled.read().unwrap() {
+            self.last_run = None;
+            return false;
+        }
+
+        let interval = Duration::from_millis(*interval_ms.read().unwrap() as u64);
+        let due = self
+            .last_run
+            .map_or(true, |last_run| last_run.elapsed() >= interval);
+        if due {
+            self.last_run = Some(Instant::now());
+        }
+
+        due
+    }
+}
+
+/// Downsamples the input into waveform chunks. The settings are only picked up once per hop so the
+/// locks aren't taken for every sample.
+#[derive(Default)]
+struct Waveform {
+    /// The number of points per chunk, zero while the waveform is disabled.
+    num_points: usize,
+    samples_per_point: usize,
+    points: Vec<f32>,
+    sum: f32,
+    num_samples: usize,
+}
+
+impl Waveform {
+    fn configure(&mut self, params: &SpaceRadioParams, sample_rate: f32) {
+        let num_points = if *params.waveform_enabled.read().unwrap() {
+            *params.waveform_points.read().unwrap() as usize
+        } else {
+            0
+        };
+        // A chunk spans the interval, or a single sample per point if that's zero
+        let chunk_samples =
+            *params.waveform_interval_ms.read().unwrap() as f32 / 1000.0 * sample_rate;
+        let samples_per_point =
+            (chunk_samples / num_points.max(1) as f32).round().max(1.0) as usize;
+
+        if num_points != self.num_points || samples_per_point != self.samples_per_point {
+            *self = Self {
+                num_points,
+                samples_per_point,
+                points: Vec::with_capacity(num_points),
+                ..Self::default()
+            };
+        }
+    }
+
+    fn push(&mut self, sample: f32, results: &AnalysisResults) {
+        if self.num_points == 0 {
+            return;
+        }
+
+        self.sum += sample;
+        self.num_samples += 1;
+        if self.num_samples < self.samples_per_point {
+            return;
+        }
+
+        self.points.push(self.sum / self.num_samples as f32);
+        self.sum = 0.0;
+        self.num_samples = 0;
+        if self.points.len() >= self.num_points {
+            std::mem::swap(&mut *results.waveform.lock().unwrap(), &mut self.points);
+            self.points.clear();
+            results.waveform_ready.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+/// The FFT of one frame of input.
+struct Frame {
+    fft: Arc<dyn Fft<f32>>,
+    /// A Hann window, normalized so a full scale sine results in an amplitude of one.
+    window: Vec<f32>,
+    spectrum: Vec<Complex<f32>>,
+    scratch: Vec<Complex<f32>>,
+}
+
+impl Frame {
+    fn new(fft: Arc<dyn Fft<f32>>) -> Self {
+        let hann: Vec<f32> = (0..FRAME_SIZE)
+            .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / FRAME_SIZE as f32).cos())
+            .collect();
+        let gain = 2.0 / hann.iter().sum::<f32>();
+
+        Self {
+            scratch: vec![Complex::default(); fft.get_inplace_scratch_len()],
+            fft,
+            window: hann.into_iter().map(|sample| sample * gain).collect(),
+            spectrum: vec![Complex::default(); FRAME_SIZE],
+        }
+    }
+
+    fn analyze(&mut self, input: &[f32]) {
+        for ((bin, &sample), &window) in self.spectrum.iter_mut().zip(input).zip(&self.window) {
+            *bin = Complex::new(sample * window, 0.0);
+        }
+        self.fft
+            .process_with_scratch(&mut self.spectrum, &mut self.scratch);
+    }
+
+    /// The amplitude of each bin up to the Nyquist frequency.
+    fn magnitudes(&self) -> impl Iterator<Item = f32> + '_ {
+        self.spectrum[..FRAME_SIZE / 2].iter().map(|bin| bin.norm())
+    }
+
+    /// The combined amplitude of the bins within `num_bands` logarithmically spaced bands.
+    fn bands(&self, num_bands: usize, sample_rate: f32) -> Vec<f32> {
+        let nyquist = sample_rate / 2.0;
+        let bin_width = sample_rate / FRAME_SIZE as f32;
+        let mut bands = vec![0.0f32; num_bands];
+        if num_bands == 0 || nyquist <= MIN_FREQUENCY {
+            return bands;
+        }
+
+        let octaves = (nyquist / MIN_FREQUENCY).log2();
+        for (bin_idx, magnitude) in self.magnitudes().enumerate().skip(1) {
+            let frequency = bin_idx as f32 * bin_width;
+            if frequency < MIN_FREQUENCY {
+                continue;
+            }
+
+            let band = ((frequency / MIN_FREQUENCY).log2() / octaves * num_bands as f32) as usize;
+            bands[band.min(num_bands - 1)] += magnitude * magnitude;
+        }
+
+        bands.iter_mut().for_each(|band| *band = band.sqrt());
+        bands
+    }
+
+    /// The spectral features, ignoring the DC bin. Silence has a centroid and rolloff of zero and a
+    /// flatness of one.
+    fn features(&self, sample_rate: f32) -> SpectralFeatures {
+        let bin_width = sample_rate / FRAME_SIZE as f32;
+        let mut magnitude_sum = 0.0;
+        let mut weighted_sum = 0.0;
+        let mut energy = 0.0;
+        let mut log_energy = 0.0;
+        let mut num_bins = 0;
+        for (bin_idx, magnitude) in self.magnitudes().enumerate().skip(1) {
+            let power = magnitude * magnitude;
+            magnitude_sum += magnitude;
+            weighted_sum += magnitude * bin_idx as f32 * bin_width;
+            energy += power;
+            log_energy += (power + f32::EPSILON).ln();
+            num_bins += 1;
+        }
+        if energy <= f32::EPSILON {
+            return SpectralFeatures {
+                centroid: 0.0,
+                flatness: 1.0,
+                rolloff: 0.0,
+            };
+        }
+
+        let mut cumulative_energy = 0.0;
+        let rolloff_bin = self
+            .magnitudes()
+            .enumerate()
+            .skip(1)
+            .find(|(_, magnitude)| {
+                cumulative_energy += magnitude * magnitude;
+                cumulative_energy >= energy * ROLLOFF_ENERGY
+            })
+            .map_or(FRAME_SIZE / 2, |(bin_idx, _)| bin_idx);
+        let geometric_mean = (log_energy / num_bins as f32).exp();
+        let arithmetic_mean = energy / num_bins as f32;
+
+        SpectralFeatures {
+            centroid: weighted_sum / magnitude_sum,
+            flatness: (geometric_mean / arithmetic_mean).min(1.0),
+            rolloff: rolloff_bin as f32 * bin_width,
+        }
+    }
+}
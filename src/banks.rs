@@ -0,0 +1,233 @@
+//! Smaller banks of integer, boolean, and enum parameters next to the float channels, for toggles
+//! and discrete selectors that floats handle awkwardly. Each bank sends its own OSC type:
+//!
+//! - `/int/<index> <value>`
+//! - `/toggle/<index> <true or false>`
+//! - `/scene/<index> <name>` with the selected scene's name, like `B`.
+//! - `/xy/<index> <x> <y>` for XY pads, whose coordinates always arrive together.
+
+use nannou_osc as osc;
+use nih_plug::prelude::*;
+use std::sync::Arc;
+
+use crate::dirty::DirtyFlags;
+use crate::SpaceRadioParams;
+
+pub const NUM_INT_PARAMS: usize = 16;
+pub const NUM_TOGGLE_PARAMS: usize = 16;
+pub const NUM_SCENE_PARAMS: usize = 4;
+pub const NUM_XY_PADS: usize = 4;
+/// The number of parameters in all banks together, see [`BankParam::flag()`].
+pub const NUM_BANK_PARAMS: usize =
+    NUM_INT_PARAMS + NUM_TOGGLE_PARAMS + NUM_SCENE_PARAMS + NUM_XY_PADS;
+
+#[derive(Params)]
+pub struct IntParams {
+    #[id = "int"]
+    pub val: IntParam,
+}
+
+#[derive(Params)]
+pub struct ToggleParams {
+    #[id = "toggle"]
+    pub val: BoolParam,
+}
+
+#[derive(Params)]
+pub struct SceneParams {
+    #[id = "scene"]
+    pub val: EnumParam<Scene>,
+}
+
+#[derive(Params)]
+pub struct XyParams {
+    #[id = "xy_x"]
+    pub x: FloatParam,
+    #[id = "xy_y"]
+    pub y: FloatParam,
+}
+
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scene {
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+}
+
+/// A parameter in one of the banks, marked as changed by the parameter's callback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BankParam {
+    Int(usize),
+    Toggle(usize),
+    Scene(usize),
+    /// Either coordinate of an XY pad.
+    Xy(usize),
+}
+
+/// A bank parameter's value, read on the audio thread and sent from the task executor.
+#[derive(Debug, Clone, Copy)]
+pub enum BankValue {
+    Int { index: usize, value: i32 },
+    Toggle { index: usize, value: bool },
+    Scene { index: usize, scene: Scene },
+    Xy { index: usize, x: f32, y: f32 },
+}
+
+pub fn int_params(dirty: &Arc<DirtyFlags>) -> Vec<IntParams> {
+    (0..NUM_INT_PARAMS)
+        .map(|index| {
+            let dirty = Arc::clone(dirty);
+            IntParams {
+                val: IntParam::new(
+                    format!("Int {}", index + 1),
+                    0,
+                    IntRange::Linear { min: 0, max: 127 },
+                )
+                .with_callback(Arc::new(move |_| {
+                    dirty.mark(BankParam::Int(index).flag());
+                })),
+            }
+        })
+        .collect()
+}
+
+pub fn toggle_params(dirty: &Arc<DirtyFlags>) -> Vec<ToggleParams> {
+    (0..NUM_TOGGLE_PARAMS)
+        .map(|index| {
+            let dirty = Arc::clone(dirty);
+            ToggleParams {
+                val: BoolParam::new(format!("Toggle {}", index + 1), false).with_callback(
+                    Arc::new(move |_| {
+                        dirty.mark(BankParam::Toggle(index).flag());
+                    }),
+                ),
+            }
+        })
+        .collect()
+}
+
+pub fn scene_params(dirty: &Arc<DirtyFlags>) -> Vec<SceneParams> {
+    (0..NUM_SCENE_PARAMS)
+        .map(|index| {
+            let dirty = Arc::clone(dirty);
+            SceneParams {
+                val: EnumParam::new(format!("Scene {}", index + 1), Scene::A).with_callback(
+                    Arc::new(move |_| {
+                        dirty.mark(BankParam::Scene(index).flag());
+                    }),
+                ),
+            }
+        })
+        .collect()
+}
+
+pub fn xy_params(dirty: &Arc<DirtyFlags>) -> Vec<XyParams> {
+    (0..NUM_XY_PADS)
+        .map(|index| {
+            let coordinate = |name: &str| {
+                let dirty = Arc::clone(dirty);
+                FloatParam::new(
+                    format!("XY {} {name}", index + 1),
+                    0.5,
+                    FloatRange::Linear { min: 0.0, max: 1.0 },
+                )
+                .with_callback(Arc::new(move |_| {
+                    dirty.mark(BankParam::Xy(index).flag());
+                }))
+            };
+
+            XyParams {
+                x: coordinate("X"),
+                y: coordinate("Y"),
+            }
+        })
+        .collect()
+}
+
+impl BankParam {
+    /// Every parameter in the banks, for resending all of them.
+    pub fn all() -> impl Iterator<Item = BankParam> {
+        let ints = (0..NUM_INT_PARAMS).map(BankParam::Int);
+        let toggles = (0..NUM_TOGGLE_PARAMS).map(BankParam::Toggle);
+        let scenes = (0..NUM_SCENE_PARAMS).map(BankParam::Scene);
+        let xy_pads = (0..NUM_XY_PADS).map(BankParam::Xy);
+        ints.chain(toggles).chain(scenes).chain(xy_pads)
+    }
+
+    /// The parameter's position among all bank parameters, which is the index of its dirty flag.
+    pub fn flag(self) -> usize {
+        match self {
+            BankParam::Int(index) => index,
+            BankParam::Toggle(index) => NUM_INT_PARAMS + index,
+            BankParam::Scene(index) => NUM_INT_PARAMS + NUM_TOGGLE_PARAMS + index,
+            BankParam::Xy(index) => NUM_INT_PARAMS + NUM_TOGGLE_PARAMS + NUM_SCENE_PARAMS + index,
+        }
+    }
+
+    /// The inverse of [`flag()`][Self::flag()].
+    pub fn from_flag(flag: usize) -> Self {
+        let flag = match flag.checked_sub(NUM_INT_PARAMS) {
+            None => return BankParam::Int(flag),
+            Some(flag) => flag,
+        };
+        let flag = match flag.checked_sub(NUM_TOGGLE_PARAMS) {
+            None => return BankParam::Toggle(flag),
+            Some(flag) => flag,
+        };
+        match flag.checked_sub(NUM_SCENE_PARAMS) {
+            None => BankParam::Scene(flag),
+            Some(flag) => BankParam::Xy(flag),
+        }
+    }
+
+    pub(crate) fn value(self, params: &SpaceRadioParams) -> BankValue {
+        match self {
+            BankParam::Int(index) => BankValue::Int {
+                index,
+                value: params.int_params[index].val.value(),
+            },
+            BankParam::Toggle(index) => BankValue::Toggle {
+                index,
+                value: params.toggle_params[index].val.value(),
+            },
+            BankParam::Scene(index) => BankValue::Scene {
+                index,
+                scene: params.scene_params[index].val.value(),
+            },
+            BankParam::Xy(index) => BankValue::Xy {
+                index,
+                x: params.xy_params[index].x.value(),
+                y: params.xy_params[index].y.value(),
+            },
+        }
+    }
+}
+
+impl BankValue {
+    pub fn to_osc(self) -> (String, Vec<osc::Type>) {
+        match self {
+            BankValue::Int { index, value } => {
+                (format!("/int/{index}"), vec![osc::Type::Int(value)])
+            }
+            BankValue::Toggle { index, value } => {
+                (format!("/toggle/{index}"), vec![osc::Type::Bool(value)])
+            }
+            BankValue::Scene { index, scene } => {
+                let name = Scene::variants()[scene.to_index()];
+                (
+                    format!("/scene/{index}"),
+                    vec![osc::Type::String(name.to_owned())],
+                )
+            }
+            BankValue::Xy { index, x, y } => (
+                format!("/xy/{index}"),
+                vec![osc::Type::Float(x), osc::Type::Float(y)],
+            ),
+        }
+    }
+}
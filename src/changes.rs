@@ -0,0 +1,104 @@
+//! Every value the channel parameters took on since the last block, in order, for when every
+//! change should be sent instead of only each channel's latest value. The parameters' callbacks
+//! record the values as the wrapper applies them, and the audio thread drains the log once per
+//! block. Recording never allocates and only ever tries to take the lock, so a change that comes
+//! in while the log is being drained or after it filled up only loses its intermediate value. The
+//! latest value is still sent as usual.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+/// How many changes the log holds between two blocks.
+const CAPACITY: usize = 1024;
+
+pub struct ChangeLog {
+    recording: AtomicBool,
+    entries: Mutex<Entries>,
+}
+
+struct Entries {
+    changes: Vec<(usize, f32)>,
+    /// Where each channel's latest change is in `changes`.
+    latest: Vec<Option<usize>>,
+}
+
+impl ChangeLog {
+    pub fn new(num_channels: usize) -> Self {
+        Self {
+            recording: AtomicBool::new(false),
+            entries: Mutex::new(Entries {
+                changes: Vec::with_capacity(CAPACITY),
+                latest: vec![None; num_channels],
+            }),
+        }
+    }
+
+    /// Changes are only recorded while this is enabled.
+    pub fn set_recording(&self, recording: bool) {
+        self.recording.store(recording, Ordering::Relaxed);
+    }
+
+    pub fn record(&self, index: usize, value: f32) {
+        if !self.recording.load(Ordering::Relaxed) {
+            return;
+        }
+
+        let Ok(mut entries) = self.entries.try_lock() else {
+            return;
+        };
+        if entries.changes.len() < CAPACITY {
+            entries.latest[index] = Some(entries.changes.len());
+            entries.changes.push((index, value));
+        }
+    }
+
+    /// Clear the log, calling `f` with every change that was followed by another change to the
+    /// same channel, in the order they were made. The channels' latest values are left out since
+    /// those are read from the parameters.
+    pub fn drain_superseded(&self, mut f: impl FnMut(usize, f32)) {
+        let Ok(mut entries) = self.entries.try_lock() else {
+            return;
+        };
+        let entries = &mut *entries;
+        for (position, &(index, value)) in entries.changes.iter().enumerate() {
+            if entries.latest[index] != Some(position) {
+                f(index, value);
+            }
+        }
+        entries.changes.clear();
+        entries.latest.fill(None);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drain(log: &ChangeLog) -> Vec<(usize, f32)> {
+        let mut changes = Vec::new();
+        log.drain_superseded(|index, value| changes.push((index, value)));
+        changes
+    }
+
+    #[test]
+    fn only_superseded_changes_are_drained() {
+        let log = ChangeLog::new(4);
+        log.set_recording(true);
+        log.record(0, 0.1);
+        log.record(1, 0.5);
+        log.record(0, 0.2);
+        log.record(0, 0.3);
+
+        assert_eq!(drain(&log), [(0, 0.1), (0, 0.2)]);
+        assert!(drain(&log).is_empty());
+    }
+
+    #[test]
+    fn nothing_is_recorded_unless_enabled() {
+        let log = ChangeLog::new(4);
+        log.record(0, 0.1);
+        log.record(0, 0.2);
+
+        assert!(drain(&log).is_empty());
+    }
+}
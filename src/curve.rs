@@ -0,0 +1,101 @@
+//! Response curves that reshape a channel's normalized value before it's scaled to the output
+//! range, so a linear fader can drive a receiver that expects a perceptual mapping.
+
+use serde::{Deserialize, Serialize};
+
+/// How steep the logarithmic and exponential curves are. Higher values bend the curve further
+/// away from a straight line.
+const STEEPNESS: f32 = 4.0;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum ResponseCurve {
+    #[default]
+    Linear,
+    /// Rises quickly at the bottom of the range and flattens out towards the top.
+    Logarithmic,
+    /// Starts out flat and rises quickly towards the top of the range. This is the inverse of
+    /// [`Logarithmic`][Self::Logarithmic].
+    Exponential,
+    /// Raises the value to the power of `factor`. Factors below one behave like the logarithmic
+    /// curve and factors above one like the exponential curve.
+    Skew { factor: f32 },
+}
+
+impl ResponseCurve {
+    /// Apply the curve to a normalized `[0, 1]` value.
+    pub fn apply(self, value: f32) -> f32 {
+        let value = value.clamp(0.0, 1.0);
+        match self {
+            ResponseCurve::Linear => value,
+            ResponseCurve::Logarithmic => (1.0 + STEEPNESS.exp_m1() * value).ln() / STEEPNESS,
+            ResponseCurve::Exponential => (STEEPNESS * value).exp_m1() / STEEPNESS.exp_m1(),
+            ResponseCurve::Skew { factor } if factor > 0.0 => value.powf(factor),
+            ResponseCurve::Skew { .. } => value,
+        }
+    }
+
+    /// The inverse of [`apply()`][Self::apply()], used for values received over OSC.
+    pub fn invert(self, value: f32) -> f32 {
+        let value = value.clamp(0.0, 1.0);
+        match self {
+            ResponseCurve::Linear => value,
+            ResponseCurve::Logarithmic => ResponseCurve::Exponential.apply(value),
+            ResponseCurve::Exponential => ResponseCurve::Logarithmic.apply(value),
+            ResponseCurve::Skew { factor } if factor > 0.0 => value.powf(factor.recip()),
+            ResponseCurve::Skew { .. } => value,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ResponseCurve::Linear => "Linear",
+            ResponseCurve::Logarithmic => "Logarithmic",
+            ResponseCurve::Exponential => "Exponential",
+            ResponseCurve::Skew { .. } => "Skew",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CURVES: [ResponseCurve; 6] = [
+        ResponseCurve::Linear,
+        ResponseCurve::Logarithmic,
+        ResponseCurve::Exponential,
+        ResponseCurve::Skew { factor: 0.5 },
+        ResponseCurve::Skew { factor: 3.0 },
+        ResponseCurve::Skew { factor: 0.0 },
+    ];
+
+    #[test]
+    fn invert_undoes_apply() {
+        for curve in CURVES {
+            for step in 0..=20 {
+                let value = step as f32 / 20.0;
+                let round_trip = curve.invert(curve.apply(value));
+                assert!(
+                    (round_trip - value).abs() < 1e-4,
+                    "{curve:?}: {value} came back as {round_trip}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn curves_keep_their_endpoints() {
+        for curve in CURVES {
+            assert!(curve.apply(0.0).abs() < 1e-6, "{curve:?}");
+            assert!((curve.apply(1.0) - 1.0).abs() < 1e-6, "{curve:?}");
+        }
+    }
+
+    #[test]
+    fn values_outside_the_range_are_clamped() {
+        for curve in CURVES {
+            assert_eq!(curve.apply(-1.0), curve.apply(0.0), "{curve:?}");
+            assert_eq!(curve.apply(2.0), curve.apply(1.0), "{curve:?}");
+        }
+    }
+}
@@ -0,0 +1,126 @@
+//! Keeps track of where messages are sent to. Edits to the destination list only take effect once
+//! it has stopped changing for a while, so typing an address one character at a time doesn't
+//! retarget the socket at every intermediate (and likely invalid) address.
+
+use nih_plug::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+use crate::socket::{Framing, Protocol};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Destination {
+    /// An IP address or a hostname like `visuals-laptop.local`.
+    pub address: String,
+    pub port: u16,
+    pub protocol: Protocol,
+    /// How packets are delimited when sending over TCP.
+    pub framing: Framing,
+    /// How many hops multicast packets may take. One keeps them on the local network.
+    pub multicast_ttl: u32,
+    /// Disabled destinations are kept around but don't receive anything.
+    pub enabled: bool,
+}
+
+impl Default for Destination {
+    fn default() -> Self {
+        Self {
+            address: String::from("127.0.0.1"),
+            port: 9009,
+            protocol: Protocol::Udp,
+            framing: Framing::Slip,
+            multicast_ttl: 1,
+            enabled: true,
+        }
+    }
+}
+
+impl Destination {
+    /// The destination as an `address:port` string. IPv6 literals may be entered with or without
+    /// brackets.
+    pub fn target(&self) -> String {
+        if self.address.contains(':') && !self.address.starts_with('[') {
+            format!("[{}]:{}", self.address, self.port)
+        } else {
+            format!("{}:{}", self.address, self.port)
+        }
+    }
+}
+
+/// Check an address that was typed in. Hostnames are only resolved once they're used, so this only
+/// catches obvious mistakes.
+pub fn validate_address(address: &str) -> Result<(), String> {
+    let address = address.trim();
+    if address.is_empty() {
+        Err(String::from("The address can't be empty"))
+    } else if address.contains(char::is_whitespace) {
+        Err(format!("{address:?} is not a valid address"))
+    } else {
+        Ok(())
+    }
+}
+
+/// Parse a port that was typed in.
+pub fn parse_port(port: &str) -> Result<u16, String> {
+    match port.trim().parse() {
+        Ok(port) if port > 0 => Ok(port),
+        _ => Err(format!("{port:?} is not a valid port")),
+    }
+}
+
+pub struct DestinationCache {
+    /// The destinations messages are currently sent to.
+    current: Vec<Destination>,
+    /// A newly configured destination list and when it was first seen.
+    pending: Option<(Vec<Destination>, Instant)>,
+}
+
+impl DestinationCache {
+    pub fn new(destinations: &[Destination]) -> Self {
+        Self {
+            current: destinations.to_vec(),
+            pending: None,
+        }
+    }
+
+    /// The destinations messages should go to right now.
+    pub fn current(&self) -> &[Destination] {
+        &self.current
+    }
+
+    /// Compare the configured destinations against the ones in use, switching over once they've
+    /// been left alone for `debounce`. Returns `true` when messages are now sent somewhere else.
+    pub fn update(&mut self, configured: &[Destination], debounce: Duration) -> bool {
+        if configured == self.current.as_slice() {
+            self.pending = None;
+            return false;
+        }
+
+        let since = match &self.pending {
+            Some((pending, since)) if pending.as_slice() == configured => *since,
+            _ => {
+                let now = Instant::now();
+                self.pending = Some((configured.to_vec(), now));
+                now
+            }
+        };
+
+        if since.elapsed() < debounce {
+            return false;
+        }
+
+        nih_log!(
+            "Retargeting OSC messages to {}",
+            configured
+                .iter()
+                .filter(|destination| destination.enabled)
+                .map(Destination::target)
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        self.current = configured.to_vec();
+        self.pending = None;
+        true
+    }
+}
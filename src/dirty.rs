@@ -0,0 +1,93 @@
+//! Lock-free flags for telling the audio thread which parameters or channels changed. Marking and
+//! draining are both wait-free and never allocate. A flag that's marked several times before it's
+//! drained is only seen once, and the value is read after draining, so the latest value wins.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+const BITS_PER_WORD: usize = u64::BITS as usize;
+
+/// A fixed number of flags, one bit per index.
+pub struct DirtyFlags {
+    words: Vec<AtomicU64>,
+}
+
+impl DirtyFlags {
+    pub fn new(len: usize) -> Self {
+        Self {
+            words: (0..len.div_ceil(BITS_PER_WORD))
+                .map(|_| AtomicU64::new(0))
+                .collect(),
+        }
+    }
+
+    /// Set the flag for `index`. Anything stored before this is visible to whoever drains it.
+    pub fn mark(&self, index: usize) {
+        let bit = 1 << (index % BITS_PER_WORD);
+        self.words[index / BITS_PER_WORD].fetch_or(bit, Ordering::Release);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words
+            .iter()
+            .all(|word| word.load(Ordering::Relaxed) == 0)
+    }
+
+    /// Clear all flags, calling `f` with the index of every flag that was set in ascending order.
+    pub fn drain(&self, mut f: impl FnMut(usize)) {
+        for (word_index, word) in self.words.iter().enumerate() {
+            // Most words are empty, and those don't need to be written to
+            if word.load(Ordering::Relaxed) == 0 {
+                continue;
+            }
+
+            let mut bits = word.swap(0, Ordering::Acquire);
+            while bits != 0 {
+                let bit = bits.trailing_zeros() as usize;
+                bits &= bits - 1;
+                f(word_index * BITS_PER_WORD + bit);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drained(flags: &DirtyFlags) -> Vec<usize> {
+        let mut indices = Vec::new();
+        flags.drain(|index| indices.push(index));
+        indices
+    }
+
+    #[test]
+    fn drains_across_word_boundaries_in_ascending_order() {
+        let flags = DirtyFlags::new(512);
+        assert!(flags.is_empty());
+
+        for index in [511, 64, 0, 63, 65, 127, 128] {
+            flags.mark(index);
+        }
+        assert!(!flags.is_empty());
+        assert_eq!(drained(&flags), [0, 63, 64, 65, 127, 128, 511]);
+        assert!(flags.is_empty());
+        assert!(drained(&flags).is_empty());
+    }
+
+    #[test]
+    fn flags_marked_repeatedly_are_drained_once() {
+        let flags = DirtyFlags::new(512);
+        for _ in 0..3 {
+            flags.mark(63);
+            flags.mark(64);
+        }
+        assert_eq!(drained(&flags), [63, 64]);
+    }
+
+    #[test]
+    fn partial_last_word() {
+        let flags = DirtyFlags::new(65);
+        flags.mark(64);
+        assert_eq!(drained(&flags), [64]);
+    }
+}
@@ -0,0 +1,209 @@
+//! Zeroconf support. The plugin advertises its OSC socket as an `_osc._udp` service, and keeps a
+//! list of the other `_osc._udp` services on the network so a destination can be picked from it
+//! instead of hunting down IP addresses.
+
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use nih_plug::prelude::*;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, TryLockError};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::destination::Destination;
+use crate::socket::OscSocket;
+use crate::SpaceRadioParams;
+
+const SERVICE_TYPE: &str = "_osc._udp.local.";
+/// How often the advertisement is checked against the settings and the socket's port.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// An OSC receiver found on the local network.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveredTarget {
+    /// The service's full mDNS name, e.g. `Resolume._osc._udp.local.`.
+    pub name: String,
+    pub address: IpAddr,
+    pub port: u16,
+}
+
+impl DiscoveredTarget {
+    /// A UDP destination pointing at this target.
+    pub fn destination(&self) -> Destination {
+        Destination {
+            address: self.address.to_string(),
+            port: self.port,
+            ..Destination::default()
+        }
+    }
+}
+
+/// Stops and joins its thread when dropped.
+pub struct Discovery {
+    targets: Arc<Mutex<Vec<DiscoveredTarget>>>,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Discovery {
+    pub(crate) fn spawn(
+        params: Arc<SpaceRadioParams>,
+        sender: Arc<Mutex<Option<OscSocket>>>,
+    ) -> Self {
+        let targets = Arc::new(Mutex::new(Vec::new()));
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = {
+            let targets = Arc::clone(&targets);
+            let stop = Arc::clone(&stop);
+            thread::Builder::new()
+                .name(String::from("space-radio-discovery"))
+                .spawn(move || run(&params, &sender, &targets, &stop))
+                .ok()
+        };
+
+        Self {
+            targets,
+            stop,
+            handle,
+        }
+    }
+
+    /// The OSC receivers currently visible on the network.
+    pub fn targets(&self) -> Vec<DiscoveredTarget> {
+        self.targets.lock().unwrap().clone()
+    }
+}
+
+impl Drop for Discovery {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn run(
+    params: &SpaceRadioParams,
+    sender: &Mutex<Option<OscSocket>>,
+    targets: &Mutex<Vec<DiscoveredTarget>>,
+    stop: &AtomicBool,
+) {
+    let daemon = match ServiceDaemon::new() {
+        Ok(daemon) => daemon,
+        Err(err) => {
+            nih_error!("Could not start mDNS, OSC targets won't be discovered: {err}");
+            return;
+        }
+    };
+    let events = match daemon.browse(SERVICE_TYPE) {
+        Ok(events) => Some(events),
+        Err(err) => {
+            nih_error!("Could not browse for OSC targets: {err}");
+            None
+        }
+    };
+
+    // The full name and port of our own advertisement
+    let mut advertised: Option<(String, u16)> = None;
+    while !stop.load(Ordering::Relaxed) {
+        // The socket is only peeked at, a send that's in progress can finish first
+        let port = match sender.try_lock() {
+            Ok(sender) => sender.as_ref().and_then(OscSocket::local_port),
+            Err(TryLockError::Poisoned(err)) => {
+                err.into_inner().as_ref().and_then(OscSocket::local_port)
+            }
+            Err(TryLockError::WouldBlock) => advertised.as_ref().map(|(_, port)| *port),
+        };
+        let port = port.filter(|_| *params.advertise_mdns.read().unwrap());
+        if advertised.as_ref().map(|(_, port)| *port) != port {
+            if let Some((fullname, _)) = advertised.take() {
+                let _ = daemon.unregister(&fullname);
+            }
+            if let Some(port) = port {
+                advertised = advertise(&daemon, port);
+            }
+        }
+
+        if let Some(events) = &events {
+            while let Ok(event) = events.try_recv() {
+                let own_name = advertised.as_ref().map(|(fullname, _)| fullname.as_str());
+                handle_event(event, own_name, targets);
+            }
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    }
+
+    if let Some((fullname, _)) = advertised {
+        let _ = daemon.unregister(&fullname);
+    }
+    let _ = daemon.shutdown();
+}
+
+fn advertise(daemon: &ServiceDaemon, port: u16) -> Option<(String, u16)> {
+    // Every instance needs a unique name, and the port is the one thing that's guaranteed to differ
+    let instance_name = format!("Space Radio {port}");
+    let host_name = format!("space-radio-{port}.local.");
+    let service = ServiceInfo::new(
+        SERVICE_TYPE,
+        &instance_name,
+        &host_name,
+        "",
+        port,
+        HashMap::<String, String>::new(),
+    )
+    .map(ServiceInfo::enable_addr_auto);
+
+    match service.and_then(|service| {
+        let fullname = service.get_fullname().to_owned();
+        daemon.register(service).map(|_| fullname)
+    }) {
+        Ok(fullname) => {
+            nih_log!("Advertising {fullname} on port {port}");
+            Some((fullname, port))
+        }
+        Err(err) => {
+            nih_warn!("Could not advertise the OSC socket over mDNS: {err}");
+            None
+        }
+    }
+}
+
+fn handle_event(
+    event: ServiceEvent,
+    own_name: Option<&str>,
+    targets: &Mutex<Vec<DiscoveredTarget>>,
+) {
+    let mut targets = targets.lock().unwrap_or_else(PoisonError::into_inner);
+    match event {
+        ServiceEvent::ServiceResolved(service) if Some(service.get_fullname()) != own_name => {
+            let name = service.get_fullname().to_owned();
+            // Prefer IPv4 since that's what most receivers listen on
+            let address = service
+                .get_addresses()
+                .iter()
+                .copied()
+                .min_by_key(|address| address.is_ipv6());
+            if let Some(address) = address {
+                let target = DiscoveredTarget {
+                    name,
+                    address,
+                    port: service.get_port(),
+                };
+                match targets.iter_mut().find(|known| known.name == target.name) {
+                    Some(known) => *known = target,
+                    None => {
+                        nih_log!("Discovered {} at {address}:{}", target.name, target.port);
+                        targets.push(target);
+                    }
+                }
+            }
+        }
+        ServiceEvent::ServiceRemoved(_, fullname) => {
+            targets.retain(|target| target.name != fullname);
+        }
+        _ => (),
+    }
+}
@@ -0,0 +1,898 @@
+//! The plugin's editor. Most of the plugin's settings are persisted fields rather than parameters,
+//! and most hosts have no way to edit those, so this is where they're configured. Edits are only
+//! made to the editor's own copy of the settings until they're applied, so half-typed addresses
+//! never reach the socket.
+
+use nih_plug::prelude::*;
+use nih_plug_egui::egui::{self, Color32};
+use nih_plug_egui::{create_egui_editor, EguiState};
+use std::any::Any;
+use std::net::Ipv4Addr;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::activity::ChannelActivity;
+use crate::curve::ResponseCurve;
+use crate::destination::{self, Destination};
+use crate::mapping;
+use crate::open_stage_control;
+use crate::profile::{self, OutputProfile};
+use crate::receiver::AutomationTarget;
+use crate::status::ConnectionStatus;
+use crate::touchosc;
+use crate::traffic::{self, Direction, Entry, TrafficLog};
+use crate::{
+    normalize_namespace, ChannelConfig, SpaceRadioParams, ValueRange, DEFAULT_CHANNEL_VALUE,
+    NUM_CHANNELS,
+};
+
+/// How long a channel's activity LED stays lit after it has sent something.
+const ACTIVITY_LED_TIME: Duration = Duration::from_millis(250);
+
+/// The number of channels the address template pane previews.
+const PREVIEW_CHANNELS: usize = 3;
+
+pub fn default_state() -> Arc<EguiState> {
+    EguiState::from_size(640, 480)
+}
+
+pub(crate) fn create(
+    params: Arc<SpaceRadioParams>,
+    automation: Arc<AutomationTarget>,
+    activity: Arc<ChannelActivity>,
+    traffic: Arc<TrafficLog>,
+    status: Arc<ConnectionStatus>,
+) -> Option<Box<dyn Editor>> {
+    let editor = create_egui_editor(
+        params.editor_state.clone(),
+        EditorState::new(&params),
+        |_, _| {},
+        move |egui_ctx, setter, state| {
+            egui::TopBottomPanel::top("status")
+                .show(egui_ctx, |ui| status_bar(ui, &params, setter, &status));
+            egui::CentralPanel::default().show(egui_ctx, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    egui::CollapsingHeader::new("Network")
+                        .default_open(true)
+                        .show(ui, |ui| network_pane(ui, &params, &mut state.network));
+                    egui::CollapsingHeader::new("Channels")
+                        .show(ui, |ui| channels_pane(ui, &params, &mut state.channels));
+                    egui::CollapsingHeader::new("Address Template")
+                        .show(ui, |ui| template_pane(ui, &params, &mut state.template));
+                    egui::CollapsingHeader::new("Mapping")
+                        .show(ui, |ui| mapping_pane(ui, &params, state));
+                    egui::CollapsingHeader::new("Activity")
+                        .show(ui, |ui| activity_pane(ui, &params, &activity));
+                    egui::CollapsingHeader::new("Traffic")
+                        .show(ui, |ui| traffic_pane(ui, &traffic, &mut state.traffic));
+                });
+            });
+        },
+    )?;
+
+    Some(Box::new(AutomationEditor { editor, automation }))
+}
+
+/// The editor's copies of the settings it edits.
+struct EditorState {
+    network: NetworkState,
+    channels: ChannelsState,
+    template: TemplateState,
+    mapping: MappingState,
+    traffic: TrafficState,
+}
+
+impl EditorState {
+    fn new(params: &SpaceRadioParams) -> Self {
+        Self {
+            network: NetworkState::new(params),
+            channels: ChannelsState::new(params),
+            template: TemplateState::new(params),
+            mapping: MappingState::default(),
+            traffic: TrafficState::default(),
+        }
+    }
+}
+
+/// The network settings as they've been typed, which may not be valid yet.
+struct NetworkState {
+    destinations: Vec<DestinationFields>,
+    namespace: String,
+    receive_port: String,
+    receive_group: String,
+    /// Why the settings couldn't be applied the last time Apply was clicked.
+    error: Option<String>,
+}
+
+struct DestinationFields {
+    /// The destination this was loaded from, which keeps the settings that aren't edited here.
+    destination: Destination,
+    address: String,
+    port: String,
+}
+
+impl NetworkState {
+    fn new(params: &SpaceRadioParams) -> Self {
+        Self {
+            destinations: params
+                .destinations
+                .read()
+                .unwrap()
+                .iter()
+                .map(|destination| DestinationFields {
+                    address: destination.address.clone(),
+                    port: destination.port.to_string(),
+                    destination: destination.clone(),
+                })
+                .collect(),
+            namespace: params.osc_namespace.read().unwrap().clone(),
+            receive_port: params.receive_port.read().unwrap().to_string(),
+            receive_group: params
+                .receive_group
+                .read()
+                .unwrap()
+                .map(|group| group.to_string())
+                .unwrap_or_default(),
+            error: None,
+        }
+    }
+
+    /// Validate all fields and write them to the parameters if they're all valid.
+    fn apply(&self, params: &SpaceRadioParams) -> Result<(), String> {
+        let destinations = self
+            .destinations
+            .iter()
+            .map(|fields| {
+                destination::validate_address(&fields.address)?;
+                Ok(Destination {
+                    address: fields.address.trim().to_owned(),
+                    port: destination::parse_port(&fields.port)?,
+                    ..fields.destination.clone()
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        let receive_port = match self.receive_port.trim() {
+            "" | "0" => 0,
+            port => destination::parse_port(port)?,
+        };
+        let receive_group = match self.receive_group.trim() {
+            "" => None,
+            group => match group.parse::<Ipv4Addr>() {
+                Ok(group) if group.is_multicast() => Some(group),
+                _ => return Err(format!("{group:?} is not an IPv4 multicast group")),
+            },
+        };
+        *params.destinations.write().unwrap() = destinations;
+        *params.osc_namespace.write().unwrap() = normalize_namespace(&self.namespace);
+        params.invalidate_addresses();
+        *params.receive_port.write().unwrap() = receive_port;
+        *params.receive_group.write().unwrap() = receive_group;
+
+        Ok(())
+    }
+}
+
+fn network_pane(ui: &mut egui::Ui, params: &SpaceRadioParams, state: &mut NetworkState) {
+    ui.label("Destinations");
+    let mut removed = None;
+    egui::Grid::new("destinations").show(ui, |ui| {
+        for (index, fields) in state.destinations.iter_mut().enumerate() {
+            ui.checkbox(&mut fields.destination.enabled, "");
+            ui.add(egui::TextEdit::singleline(&mut fields.address).hint_text("Address"));
+            ui.add(
+                egui::TextEdit::singleline(&mut fields.port)
+                    .hint_text("Port")
+                    .desired_width(60.0),
+            );
+            if ui.button("Remove").clicked() {
+                removed = Some(index);
+            }
+            ui.end_row();
+        }
+    });
+    if let Some(index) = removed {
+        state.destinations.remove(index);
+    }
+    if ui.button("Add Destination").clicked() {
+        let destination = Destination::default();
+        state.destinations.push(DestinationFields {
+            address: destination.address.clone(),
+            port: destination.port.to_string(),
+            destination,
+        });
+    }
+
+    ui.separator();
+    egui::Grid::new("network").show(ui, |ui| {
+        ui.label("Namespace");
+        ui.add(egui::TextEdit::singleline(&mut state.namespace).hint_text("/spaceradio"));
+        ui.end_row();
+
+        ui.label("Receive Port");
+        ui.add(egui::TextEdit::singleline(&mut state.receive_port).hint_text("Off"));
+        ui.end_row();
+
+        ui.label("Receive Group");
+        ui.add(egui::TextEdit::singleline(&mut state.receive_group).hint_text("None"));
+        ui.end_row();
+    });
+
+    let mut accept_all = *params.accept_all.read().unwrap();
+    if ui
+        .checkbox(&mut accept_all, "Accept /all messages")
+        .on_hover_text("Messages to the namespace followed by /all set every channel in use")
+        .changed()
+    {
+        *params.accept_all.write().unwrap() = accept_all;
+    }
+    let mut pass_through = *params.pass_through.read().unwrap();
+    if ui
+        .checkbox(&mut pass_through, "Pass through other messages")
+        .on_hover_text("Forwards messages that don't control the plugin to the destinations")
+        .changed()
+    {
+        *params.pass_through.write().unwrap() = pass_through;
+    }
+
+    ui.horizontal(|ui| {
+        if ui.button("Apply").clicked() {
+            state.error = state.apply(params).err();
+            if state.error.is_none() {
+                state.namespace = params.osc_namespace.read().unwrap().clone();
+            }
+        }
+        if ui.button("Revert").clicked() {
+            *state = NetworkState::new(params);
+        }
+    });
+    if let Some(error) = &state.error {
+        ui.colored_label(Color32::RED, error);
+    }
+}
+
+/// A green or red indicator for whether the sockets work, with everything that's currently wrong.
+fn status_bar(
+    ui: &mut egui::Ui,
+    params: &SpaceRadioParams,
+    setter: &ParamSetter,
+    status: &ConnectionStatus,
+) {
+    // Errors can clear up on their own, so this needs to keep checking
+    ui.ctx().request_repaint_after(Duration::from_millis(500));
+
+    let errors = status.errors();
+    ui.horizontal(|ui| {
+        let color = if errors.is_empty() {
+            Color32::GREEN
+        } else {
+            Color32::RED
+        };
+        let (rect, _) = ui.allocate_exact_size(egui::vec2(12.0, 12.0), egui::Sense::hover());
+        ui.painter().circle_filled(rect.center(), 6.0, color);
+        ui.label(if errors.is_empty() {
+            "Connected"
+        } else {
+            "Connection problems"
+        });
+
+        // The kill switch is a parameter so it can also be automated and mapped to a controller
+        let muted = params.output_muted.value();
+        let label = egui::RichText::new("Mute Output");
+        let label = if muted {
+            label.color(Color32::RED)
+        } else {
+            label
+        };
+        if ui.selectable_label(muted, label).clicked() {
+            setter.begin_set_parameter(&params.output_muted);
+            setter.set_parameter(&params.output_muted, !muted);
+            setter.end_set_parameter(&params.output_muted);
+        }
+    });
+    for error in errors {
+        ui.colored_label(Color32::RED, error);
+    }
+}
+
+/// The channel names and groups as they're being typed. They're stored once their field loses
+/// focus.
+struct ChannelsState {
+    names: Vec<String>,
+    groups: Vec<String>,
+    /// Comma separated group names for splitting the channels into groups of `group_size`.
+    split_names: String,
+    group_size: u32,
+}
+
+impl ChannelsState {
+    fn new(params: &SpaceRadioParams) -> Self {
+        let channels = params.channels.read().unwrap();
+        let field = |get: fn(&ChannelConfig) -> &Option<String>| {
+            (0..NUM_CHANNELS)
+                .map(|index| channels.get(index).and_then(|channel| get(channel).clone()))
+                .map(Option::unwrap_or_default)
+                .collect()
+        };
+
+        Self {
+            names: field(|channel| &channel.name),
+            groups: field(|channel| &channel.group),
+            split_names: String::new(),
+            group_size: 16,
+        }
+    }
+
+    /// Put consecutive runs of `group_size` channels in the groups from `split_names`. Channels
+    /// after the last group keep the group they were in.
+    fn split_into_groups(&mut self, params: &SpaceRadioParams) {
+        let names = self
+            .split_names
+            .split(',')
+            .filter_map(non_empty)
+            .collect::<Vec<_>>();
+        let group_size = self.group_size.max(1) as usize;
+
+        let mut channels = params.channels.write().unwrap();
+        for (group_index, name) in names.iter().enumerate() {
+            let start = group_index * group_size;
+            for index in start..(start + group_size).min(NUM_CHANNELS) {
+                if let Some(channel) = channels.get_mut(index) {
+                    channel.group = Some(name.clone());
+                }
+                self.groups[index] = name.clone();
+            }
+        }
+        params.invalidate_addresses();
+    }
+}
+
+fn channels_pane(ui: &mut egui::Ui, params: &SpaceRadioParams, state: &mut ChannelsState) {
+    let mut channel_count = params.channel_count() as u32;
+    ui.horizontal(|ui| {
+        ui.label("Channels in use");
+        let drag = egui::DragValue::new(&mut channel_count).clamp_range(1..=NUM_CHANNELS as u32);
+        if ui.add(drag).changed() {
+            *params.channel_count.write().unwrap() = channel_count;
+        }
+    });
+
+    let mut names_in_addresses = *params.names_in_addresses.read().unwrap();
+    if ui
+        .checkbox(&mut names_in_addresses, "Use names in addresses")
+        .changed()
+    {
+        *params.names_in_addresses.write().unwrap() = names_in_addresses;
+        params.invalidate_addresses();
+    }
+
+    let mut interpolate = *params.interpolate.read().unwrap();
+    if ui
+        .checkbox(&mut interpolate, "Interpolate between changes")
+        .on_hover_text("Sends ramps at the smoothing rate instead of jumping to each new value")
+        .changed()
+    {
+        *params.interpolate.write().unwrap() = interpolate;
+    }
+
+    let mut send_every_change = *params.send_every_change.read().unwrap();
+    if ui
+        .checkbox(&mut send_every_change, "Send every change")
+        .on_hover_text("Sends every value a channel moved through during a block in one bundle")
+        .changed()
+    {
+        *params.send_every_change.write().unwrap() = send_every_change;
+    }
+
+    ui.horizontal(|ui| {
+        ui.label("Split into groups");
+        ui.add(egui::TextEdit::singleline(&mut state.split_names).hint_text("visuals, lights"));
+        ui.label("of");
+        ui.add(egui::DragValue::new(&mut state.group_size).clamp_range(1..=NUM_CHANNELS as u32));
+        if ui.button("Assign").clicked() {
+            state.split_into_groups(params);
+        }
+    });
+
+    egui::Grid::new("channels").show(ui, |ui| {
+        let fields = state.names.iter_mut().zip(&mut state.groups);
+        for (index, (name, group)) in fields.enumerate().take(channel_count as usize) {
+            ui.label(format!("Ch. {}", index + 1));
+            mute_field(ui, params, index);
+            let name_response = ui.add(egui::TextEdit::singleline(name).hint_text("Unnamed"));
+            let group_response = ui.add(egui::TextEdit::singleline(group).hint_text("No group"));
+            if name_response.lost_focus() || group_response.lost_focus() {
+                if let Some(channel) = params.channels.write().unwrap().get_mut(index) {
+                    channel.name = non_empty(name);
+                    channel.group = non_empty(group);
+                }
+                params.invalidate_addresses();
+            }
+            range_fields(ui, params, index);
+            curve_fields(ui, params, index);
+            scale_offset_fields(ui, params, index);
+            step_field(ui, params, index);
+            home_field(ui, params, index);
+            learn_fields(ui, params, index);
+            ui.end_row();
+        }
+    });
+}
+
+fn mute_field(ui: &mut egui::Ui, params: &SpaceRadioParams, index: usize) {
+    let mut muted = params
+        .channels
+        .read()
+        .unwrap()
+        .get(index)
+        .map_or(false, |channel| channel.muted);
+    if ui.checkbox(&mut muted, "Mute").changed() {
+        if let Some(channel) = params.channels.write().unwrap().get_mut(index) {
+            channel.muted = muted;
+        }
+    }
+}
+
+/// Gives a channel its own output range instead of the profile's. A new range starts out as the
+/// profile's range.
+fn range_fields(ui: &mut egui::Ui, params: &SpaceRadioParams, index: usize) {
+    let range = params
+        .channels
+        .read()
+        .unwrap()
+        .get(index)
+        .and_then(|channel| channel.range);
+    let mut enabled = range.is_some();
+    let mut edited = range.unwrap_or_else(|| {
+        let profile = params.active_profile();
+        ValueRange {
+            min: profile.min,
+            max: profile.max,
+        }
+    });
+
+    let changed = ui
+        .horizontal(|ui| {
+            let mut changed = ui.checkbox(&mut enabled, "Range").changed();
+            ui.add_enabled_ui(enabled, |ui| {
+                changed |= ui.add(egui::DragValue::new(&mut edited.min)).changed();
+                ui.label("to");
+                changed |= ui.add(egui::DragValue::new(&mut edited.max)).changed();
+            });
+            changed
+        })
+        .inner;
+    if changed {
+        if let Some(channel) = params.channels.write().unwrap().get_mut(index) {
+            channel.range = enabled.then_some(edited);
+        }
+    }
+}
+
+/// The `a` and `b` in `a * x + b` that's applied to the channel's transmitted values.
+fn scale_offset_fields(ui: &mut egui::Ui, params: &SpaceRadioParams, index: usize) {
+    let (mut scale, mut offset) = params
+        .channels
+        .read()
+        .unwrap()
+        .get(index)
+        .map_or((1.0, 0.0), |channel| (channel.scale, channel.offset));
+
+    let changed = ui
+        .horizontal(|ui| {
+            let scale = egui::DragValue::new(&mut scale).speed(0.01).prefix("×");
+            let offset = egui::DragValue::new(&mut offset).speed(0.01).prefix("+");
+            ui.add(scale).changed() | ui.add(offset).changed()
+        })
+        .inner;
+    if changed {
+        if let Some(channel) = params.channels.write().unwrap().get_mut(index) {
+            channel.scale = scale;
+            channel.offset = offset;
+        }
+    }
+}
+
+/// The channel's step size, where zero sends values as they are.
+fn step_field(ui: &mut egui::Ui, params: &SpaceRadioParams, index: usize) {
+    let mut step = params
+        .channels
+        .read()
+        .unwrap()
+        .get(index)
+        .map_or(0.0, |channel| channel.step);
+    let drag = egui::DragValue::new(&mut step)
+        .clamp_range(0.0..=f32::MAX)
+        .speed(0.01)
+        .prefix("Step ");
+    if ui.add(drag).changed() {
+        if let Some(channel) = params.channels.write().unwrap().get_mut(index) {
+            channel.step = step;
+        }
+    }
+}
+
+/// The normalized value the channel is ramped to when it's sent home.
+fn home_field(ui: &mut egui::Ui, params: &SpaceRadioParams, index: usize) {
+    let mut home = params
+        .channels
+        .read()
+        .unwrap()
+        .get(index)
+        .map_or(DEFAULT_CHANNEL_VALUE, |channel| channel.home);
+    let drag = egui::DragValue::new(&mut home)
+        .clamp_range(0.0..=1.0)
+        .speed(0.01)
+        .prefix("Home ");
+    if ui.add(drag).changed() {
+        if let Some(channel) = params.channels.write().unwrap().get_mut(index) {
+            channel.home = home;
+        }
+    }
+}
+
+/// Arms the channel to learn the address of the next OSC message or the next MIDI CC that comes
+/// in, whichever is first, and shows what it learned so it can be forgotten again.
+fn learn_fields(ui: &mut egui::Ui, params: &SpaceRadioParams, index: usize) {
+    let (input_address, midi_cc) = params
+        .channels
+        .read()
+        .unwrap()
+        .get(index)
+        .map_or((None, None), |channel| {
+            (channel.input_address.clone(), channel.midi_cc)
+        });
+
+    ui.horizontal(|ui| {
+        let mut learn_channel = params.learn_channel.lock().unwrap();
+        let armed = *learn_channel == Some(index);
+        if ui
+            .selectable_label(armed, "Learn")
+            .on_hover_text("Binds the next OSC address or MIDI CC that comes in")
+            .clicked()
+        {
+            *learn_channel = (!armed).then_some(index);
+        }
+        drop(learn_channel);
+        if armed {
+            // The channel stops learning on another thread
+            ui.ctx().request_repaint();
+        }
+
+        ui.label(input_address.as_deref().unwrap_or("No input address"));
+        let clear = egui::Button::new("Clear");
+        if ui.add_enabled(input_address.is_some(), clear).clicked() {
+            if let Some(channel) = params.channels.write().unwrap().get_mut(index) {
+                channel.input_address = None;
+            }
+            params.invalidate_addresses();
+        }
+
+        match midi_cc {
+            Some(midi_cc) => ui.label(midi_cc.to_string()),
+            None => ui.label("No MIDI CC"),
+        };
+        let clear = egui::Button::new("Clear");
+        if ui.add_enabled(midi_cc.is_some(), clear).clicked() {
+            if let Some(channel) = params.channels.write().unwrap().get_mut(index) {
+                channel.midi_cc = None;
+            }
+        }
+    });
+}
+
+/// The channel's response curve, with the factor for skewed curves.
+fn curve_fields(ui: &mut egui::Ui, params: &SpaceRadioParams, index: usize) {
+    let curve = params
+        .channels
+        .read()
+        .unwrap()
+        .get(index)
+        .map(|channel| channel.curve)
+        .unwrap_or_default();
+    let mut edited = curve;
+
+    ui.horizontal(|ui| {
+        egui::ComboBox::from_id_source(("curve", index))
+            .selected_text(edited.name())
+            .show_ui(ui, |ui| {
+                let skew = match curve {
+                    ResponseCurve::Skew { .. } => curve,
+                    _ => ResponseCurve::Skew { factor: 2.0 },
+                };
+                for option in [
+                    ResponseCurve::Linear,
+                    ResponseCurve::Logarithmic,
+                    ResponseCurve::Exponential,
+                    skew,
+                ] {
+                    ui.selectable_value(&mut edited, option, option.name());
+                }
+            });
+        if let ResponseCurve::Skew { factor } = &mut edited {
+            ui.add(
+                egui::DragValue::new(factor)
+                    .clamp_range(0.1..=10.0)
+                    .speed(0.01),
+            );
+        }
+    });
+
+    if edited != curve {
+        if let Some(channel) = params.channels.write().unwrap().get_mut(index) {
+            channel.curve = edited;
+        }
+    }
+}
+
+fn non_empty(text: &str) -> Option<String> {
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_owned())
+}
+
+/// The active profile's address template as it's being typed.
+struct TemplateState {
+    template: String,
+    /// Why the template couldn't be applied the last time Apply was clicked.
+    error: Option<String>,
+}
+
+impl TemplateState {
+    fn new(params: &SpaceRadioParams) -> Self {
+        Self {
+            template: params.active_profile().address,
+            error: None,
+        }
+    }
+
+    fn apply(&self, params: &SpaceRadioParams) -> Result<(), String> {
+        profile::validate_template(&self.template)?;
+
+        let active_profile = params.active_profile.read().unwrap();
+        let mut profiles = params.profiles.write().unwrap();
+        match profiles
+            .iter_mut()
+            .find(|profile| profile.name == *active_profile)
+        {
+            Some(profile) => profile.address = self.template.clone(),
+            None => profiles.push(OutputProfile {
+                name: active_profile.clone(),
+                address: self.template.clone(),
+                ..OutputProfile::default()
+            }),
+        }
+        params.invalidate_addresses();
+
+        Ok(())
+    }
+}
+
+/// Edits the active profile's address template, with a preview of the first few channels'
+/// addresses.
+fn template_pane(ui: &mut egui::Ui, params: &SpaceRadioParams, state: &mut TemplateState) {
+    ui.label(format!(
+        "The address template for the {:?} profile. {{index}}, {{name}}, {{group}}, and {{member}} \
+         are replaced by each channel's index, name, group, and position within its group.",
+        *params.active_profile.read().unwrap()
+    ));
+    ui.text_edit_singleline(&mut state.template);
+
+    let mut profile = params.active_profile();
+    profile.address = state.template.clone();
+    let namespace = params.osc_namespace.read().unwrap().clone();
+    let namespace = namespace.trim_end_matches('/');
+    let channels = params.channels.read().unwrap();
+    egui::Grid::new("template_preview").show(ui, |ui| {
+        for index in 0..PREVIEW_CHANNELS {
+            ui.label(format!("Ch. {}", index + 1));
+            ui.monospace(format!(
+                "{namespace}{}",
+                params.channel_address(index, &channels, &profile)
+            ));
+            ui.end_row();
+        }
+    });
+    drop(channels);
+
+    ui.horizontal(|ui| {
+        if ui.button("Apply").clicked() {
+            state.error = state.apply(params).err();
+        }
+        if ui.button("Revert").clicked() {
+            *state = TemplateState::new(params);
+        }
+    });
+    if let Some(error) = &state.error {
+        ui.colored_label(Color32::RED, error);
+    }
+}
+
+/// The file the mapping is exported to and imported from.
+#[derive(Default)]
+struct MappingState {
+    path: String,
+    /// What happened the last time Export or Import was clicked.
+    result: Option<Result<String, String>>,
+}
+
+/// Exports the destinations, addresses, and channel settings to a JSON file, or imports them. The
+/// channels can also be exported as a TouchOSC layout or an Open Stage Control session.
+fn mapping_pane(ui: &mut egui::Ui, params: &SpaceRadioParams, state: &mut EditorState) {
+    ui.horizontal(|ui| {
+        ui.label("File");
+        ui.add(egui::TextEdit::singleline(&mut state.mapping.path).hint_text("mapping.json"));
+    });
+
+    let path = Path::new(state.mapping.path.trim());
+    ui.horizontal(|ui| {
+        if ui.button("Export").clicked() {
+            state.mapping.result = Some(
+                mapping::export(params, path).map(|()| format!("Exported to {}", path.display())),
+            );
+        }
+        if ui.button("Import").clicked() {
+            let result = mapping::import(params, path);
+            if result.is_ok() {
+                // The other panes' copies of the settings are stale now
+                state.network = NetworkState::new(params);
+                state.channels = ChannelsState::new(params);
+                state.template = TemplateState::new(params);
+            }
+            state.mapping.result = Some(result.map(|()| format!("Imported {}", path.display())));
+        }
+        if ui.button("Export TouchOSC Layout").clicked() {
+            state.mapping.result = Some(
+                touchosc::export(params, path).map(|()| format!("Exported to {}", path.display())),
+            );
+        }
+        if ui.button("Export Open Stage Control Session").clicked() {
+            state.mapping.result = Some(
+                open_stage_control::export(params, path)
+                    .map(|()| format!("Exported to {}", path.display())),
+            );
+        }
+    });
+    match &state.mapping.result {
+        Some(Ok(message)) => {
+            ui.label(message);
+        }
+        Some(Err(error)) => {
+            ui.colored_label(Color32::RED, error);
+        }
+        None => (),
+    }
+}
+
+/// An LED per channel that lights up when the channel sends something, with the value it sent.
+fn activity_pane(ui: &mut egui::Ui, params: &SpaceRadioParams, activity: &ChannelActivity) {
+    // The LEDs fade out on their own, so this needs to keep redrawing while it's open
+    ui.ctx().request_repaint();
+
+    egui::Grid::new("activity").num_columns(8).show(ui, |ui| {
+        let channel_count = params.channel_count();
+        for (index, sent) in activity
+            .last_sent()
+            .into_iter()
+            .enumerate()
+            .take(channel_count)
+        {
+            let brightness = sent.as_ref().map_or(0.0, |sent| {
+                1.0 - sent.time.elapsed().as_secs_f32() / ACTIVITY_LED_TIME.as_secs_f32()
+            });
+            let color = Color32::from_rgb(40, 40 + (brightness.clamp(0.0, 1.0) * 200.0) as u8, 40);
+
+            ui.horizontal(|ui| {
+                let (rect, _) =
+                    ui.allocate_exact_size(egui::vec2(10.0, 10.0), egui::Sense::hover());
+                ui.painter().circle_filled(rect.center(), 5.0, color);
+                let value = sent.map_or_else(
+                    || String::from("-"),
+                    |sent| traffic::format_arg(&sent.value),
+                );
+                ui.label(format!("{}: {value}", index + 1));
+            });
+            if (index + 1) % 8 == 0 {
+                ui.end_row();
+            }
+        }
+    });
+}
+
+#[derive(Default)]
+struct TrafficState {
+    /// The entries that were shown when the console was paused.
+    paused: Option<Vec<Entry>>,
+}
+
+/// A scrolling log of recent messages, see the `traffic` module.
+fn traffic_pane(ui: &mut egui::Ui, traffic: &TrafficLog, state: &mut TrafficState) {
+    traffic.watch();
+    ui.ctx().request_repaint();
+
+    ui.horizontal(|ui| {
+        let mut paused = state.paused.is_some();
+        if ui.checkbox(&mut paused, "Pause").changed() {
+            state.paused = paused.then(|| traffic.entries());
+        }
+        if ui.button("Clear").clicked() {
+            traffic.clear();
+            state.paused = state.paused.as_ref().map(|_| Vec::new());
+        }
+    });
+
+    let entries = match &state.paused {
+        Some(entries) => entries.clone(),
+        None => traffic.entries(),
+    };
+    egui::ScrollArea::vertical()
+        .id_source("traffic")
+        .max_height(240.0)
+        .stick_to_bottom(true)
+        .show(ui, |ui| {
+            for entry in entries {
+                let direction = match entry.direction {
+                    Direction::Sent => "->",
+                    Direction::Received => "<-",
+                };
+                ui.monospace(format!(
+                    "{:>9.3} {direction} {}",
+                    entry.time.as_secs_f32(),
+                    entry.text
+                ));
+            }
+        });
+}
+
+/// Attaches the editor's GUI context to the [`AutomationTarget`] while the editor is open, so
+/// values received over OSC can be recorded as automation.
+struct AutomationEditor {
+    editor: Box<dyn Editor>,
+    automation: Arc<AutomationTarget>,
+}
+
+/// Detaches the GUI context when the editor closes.
+struct AttachedHandle {
+    _handle: Box<dyn Any + Send>,
+    automation: Arc<AutomationTarget>,
+}
+
+impl Editor for AutomationEditor {
+    fn spawn(
+        &self,
+        parent: ParentWindowHandle,
+        context: Arc<dyn GuiContext>,
+    ) -> Box<dyn Any + Send> {
+        self.automation.attach(Arc::clone(&context));
+
+        Box::new(AttachedHandle {
+            _handle: self.editor.spawn(parent, context),
+            automation: Arc::clone(&self.automation),
+        })
+    }
+
+    fn size(&self) -> (u32, u32) {
+        self.editor.size()
+    }
+
+    fn set_scale_factor(&self, factor: f32) -> bool {
+        self.editor.set_scale_factor(factor)
+    }
+
+    fn param_value_changed(&self, id: &str, normalized_value: f32) {
+        self.editor.param_value_changed(id, normalized_value)
+    }
+
+    fn param_modulation_changed(&self, id: &str, modulation_offset: f32) {
+        self.editor.param_modulation_changed(id, modulation_offset)
+    }
+
+    fn param_values_changed(&self) {
+        self.editor.param_values_changed()
+    }
+}
+
+impl Drop for AttachedHandle {
+    fn drop(&mut self) {
+        self.automation.detach();
+    }
+}
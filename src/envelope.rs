@@ -0,0 +1,103 @@
+//! Envelope followers on the plugin's audio input, turning any audio into slow control signals for
+//! driving visuals.
+//!
+//! - `/envelope <channel 1> <channel 2> ...` with each input channel's envelope as a linear gain.
+
+use nih_plug::prelude::*;
+
+use crate::analysis::{ChannelValues, MAX_CHANNELS};
+
+#[derive(Params)]
+pub struct EnvelopeParams {
+    #[id = "env_on"]
+    pub enabled: BoolParam,
+    /// How quickly the envelope rises to follow louder input.
+    #[id = "env_attack"]
+    pub attack: FloatParam,
+    /// How quickly the envelope falls once the input gets quieter.
+    #[id = "env_release"]
+    pub release: FloatParam,
+}
+
+impl Default for EnvelopeParams {
+    fn default() -> Self {
+        Self {
+            enabled: BoolParam::new("Envelope Follower", false),
+            attack: FloatParam::new(
+                "Envelope Attack",
+                10.0,
+                FloatRange::Skewed {
+                    min: 0.1,
+                    max: 1000.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_unit(" ms")
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
+            release: FloatParam::new(
+                "Envelope Release",
+                300.0,
+                FloatRange::Skewed {
+                    min: 1.0,
+                    max: 10000.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_unit(" ms")
+            .with_step_size(1.0),
+        }
+    }
+}
+
+/// A peak envelope follower per input channel.
+#[derive(Default)]
+pub struct EnvelopeFollower {
+    envelopes: [f32; MAX_CHANNELS],
+    num_channels: usize,
+    /// Samples processed since the envelopes were last sent.
+    num_samples: usize,
+}
+
+impl EnvelopeFollower {
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Follow the next block of input. Returns the envelopes once `interval_ms` has passed since
+    /// they were last returned, or after every block if that's zero.
+    pub fn process(
+        &mut self,
+        buffer: &Buffer,
+        params: &EnvelopeParams,
+        interval_ms: u32,
+        sample_rate: f32,
+    ) -> Option<ChannelValues> {
+        let attack = coefficient(params.attack.value(), sample_rate);
+        let release = coefficient(params.release.value(), sample_rate);
+        let channels = buffer.as_slice_immutable();
+        self.num_channels = channels.len().min(MAX_CHANNELS);
+        for (envelope, samples) in self.envelopes.iter_mut().zip(channels.iter()) {
+            for &sample in samples.iter() {
+                let level = sample.abs();
+                let coefficient = if level > *envelope { attack } else { release };
+                *envelope += (level - *envelope) * coefficient;
+            }
+        }
+        self.num_samples += buffer.samples();
+
+        let interval_samples = (interval_ms as f32 / 1000.0 * sample_rate) as usize;
+        if self.num_samples == 0 || self.num_samples < interval_samples {
+            return None;
+        }
+
+        self.num_samples = 0;
+        Some(ChannelValues::new(
+            self.envelopes[..self.num_channels].iter().copied(),
+        ))
+    }
+}
+
+/// The per-sample smoothing coefficient for a time constant in milliseconds.
+fn coefficient(time_ms: f32, sample_rate: f32) -> f32 {
+    1.0 - (-1.0 / (time_ms / 1000.0 * sample_rate).max(1.0)).exp()
+}
@@ -0,0 +1,123 @@
+//! Detects whether there's any audio on the plugin's input so channels can be configured to only
+//! transmit while the source is actually playing.
+
+use nih_plug::prelude::*;
+
+#[derive(Params)]
+pub struct GateParams {
+    /// The input counts as present once its envelope rises above this level.
+    #[id = "gate_threshold"]
+    pub threshold: FloatParam,
+    #[id = "gate_attack"]
+    pub attack: FloatParam,
+    /// How long the gate stays open after the envelope has dropped below the threshold.
+    #[id = "gate_hold"]
+    pub hold: FloatParam,
+    #[id = "gate_release"]
+    pub release: FloatParam,
+}
+
+impl Default for GateParams {
+    fn default() -> Self {
+        Self {
+            threshold: FloatParam::new(
+                "Gate Threshold",
+                -50.0,
+                FloatRange::Linear {
+                    min: -80.0,
+                    max: 0.0,
+                },
+            )
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
+            attack: FloatParam::new(
+                "Gate Attack",
+                5.0,
+                FloatRange::Skewed {
+                    min: 0.1,
+                    max: 500.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_unit(" ms")
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
+            hold: FloatParam::new(
+                "Gate Hold",
+                250.0,
+                FloatRange::Skewed {
+                    min: 0.0,
+                    max: 2000.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_unit(" ms")
+            .with_step_size(1.0),
+            release: FloatParam::new(
+                "Gate Release",
+                200.0,
+                FloatRange::Skewed {
+                    min: 1.0,
+                    max: 5000.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_unit(" ms")
+            .with_step_size(1.0),
+        }
+    }
+}
+
+/// A block-rate envelope follower with a threshold and a hold time. The attack and release smooth
+/// the envelope so short dips and spikes don't make the gate chatter.
+#[derive(Default)]
+pub struct InputGate {
+    envelope: f32,
+    /// Seconds left before the gate closes once the envelope has dropped below the threshold.
+    hold_remaining: f32,
+    open: bool,
+}
+
+impl InputGate {
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Update the gate with the next block of input and return whether it's open.
+    pub fn process(&mut self, buffer: &Buffer, params: &GateParams, sample_rate: f32) -> bool {
+        let num_samples = buffer.samples();
+        if num_samples == 0 {
+            return self.open;
+        }
+
+        let peak = buffer
+            .as_slice_immutable()
+            .iter()
+            .flat_map(|channel| channel.iter())
+            .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+
+        let block_seconds = num_samples as f32 / sample_rate;
+        let time_ms = if peak > self.envelope {
+            params.attack.value()
+        } else {
+            params.release.value()
+        };
+        let coefficient = 1.0 - (-block_seconds / (time_ms / 1000.0).max(1e-6)).exp();
+        self.envelope += (peak - self.envelope) * coefficient;
+
+        if self.envelope >= util::db_to_gain(params.threshold.value()) {
+            self.open = true;
+            self.hold_remaining = params.hold.value() / 1000.0;
+        } else if self.open {
+            self.hold_remaining -= block_seconds;
+            if self.hold_remaining <= 0.0 {
+                self.open = false;
+            }
+        }
+
+        self.open
+    }
+}
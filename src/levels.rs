@@ -0,0 +1,69 @@
+//! Measures the level of the plugin's audio input, so it can double as an audio-reactive data
+//! source for visuals without a second analysis plugin.
+//!
+//! - `/level/rms <channel 1> <channel 2> ...`
+//! - `/level/peak <channel 1> <channel 2> ...`
+//!
+//! Both are linear gains, measured over the time since the previous message.
+
+use nih_plug::prelude::*;
+
+use crate::analysis::{ChannelValues, MAX_CHANNELS};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Levels {
+    pub rms: ChannelValues,
+    pub peak: ChannelValues,
+}
+
+#[derive(Default)]
+pub struct LevelMeter {
+    sum_squares: [f64; MAX_CHANNELS],
+    peak: [f32; MAX_CHANNELS],
+    num_channels: usize,
+    /// Samples measured since the levels were last sent.
+    num_samples: usize,
+}
+
+impl LevelMeter {
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Measure the next block of input. Returns the levels once `interval_ms` has passed since they
+    /// were last returned, or after every block if that's zero.
+    pub fn process(
+        &mut self,
+        buffer: &Buffer,
+        interval_ms: u32,
+        sample_rate: f32,
+    ) -> Option<Levels> {
+        let channels = buffer.as_slice_immutable();
+        self.num_channels = channels.len().min(MAX_CHANNELS);
+        for (channel, samples) in channels.iter().take(MAX_CHANNELS).enumerate() {
+            for &sample in samples.iter() {
+                self.sum_squares[channel] += (sample * sample) as f64;
+                self.peak[channel] = self.peak[channel].max(sample.abs());
+            }
+        }
+        self.num_samples += buffer.samples();
+
+        let interval_samples = (interval_ms as f32 / 1000.0 * sample_rate) as usize;
+        if self.num_samples == 0 || self.num_samples < interval_samples {
+            return None;
+        }
+
+        let num_samples = self.num_samples as f64;
+        let levels = Levels {
+            rms: ChannelValues::new(
+                self.sum_squares[..self.num_channels]
+                    .iter()
+                    .map(|&sum_squares| (sum_squares / num_samples).sqrt() as f32),
+            ),
+            peak: ChannelValues::new(self.peak[..self.num_channels].iter().copied()),
+        };
+        self.reset();
+
+        Some(levels)
+    }
+}
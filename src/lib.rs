@@ -1,16 +1,225 @@
-use dashmap::DashSet;
 use nannou_osc as osc;
 use nih_plug::prelude::*;
-use osc::Sender;
+use nih_plug_egui::EguiState;
+use serde::{Deserialize, Serialize};
 use std::{
-    sync::{mpsc, Arc, Mutex, RwLock},
-    thread,
+    net::Ipv4Addr,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc, Arc, Mutex, PoisonError, RwLock,
+    },
+    time::{Duration, Instant, SystemTime},
 };
 
+use activity::ChannelActivity;
+use analysis::{
+    AnalysisInput, AnalysisResults, AnalysisSource, Analyzer, ChannelValues, SpectralFeatures,
+};
+use banks::{
+    BankParam, BankValue, IntParams, SceneParams, ToggleParams, XyParams, NUM_BANK_PARAMS,
+};
+use changes::ChangeLog;
+use clock::{Clock, ClockMessage};
+use curve::ResponseCurve;
+use destination::{Destination, DestinationCache};
+use dirty::DirtyFlags;
+use discovery::Discovery;
+use envelope::{EnvelopeFollower, EnvelopeParams};
+use gate::{GateParams, InputGate};
+use levels::{LevelMeter, Levels};
+use link::{LinkClock, LinkMessage, LinkSession};
+use loudness::{Loudness, LoudnessMeter};
+use metronome::{Metronome, MetronomeParams};
+use midi::{MidiCc, MidiMessage, MpeTracker};
+use migration::StateVersion;
+use oscquery::OscQueryServer;
+use pitch::Pitch;
+use profile::{OutputProfile, ValueType};
+use receiver::{AutomationTarget, OscReceiver, ReceiverContext, RemoteValues};
+use resolver::{ResolvedAddresses, Resolver};
+use routing::Route;
+use smoothing::ChannelSmoothers;
+use snapshot::Snapshot;
+use socket::{Framing, LocalBind, OscSocket, Protocol};
+use status::ConnectionStatus;
+use stereo::{StereoImage, StereoMeter};
+use sync::SyncState;
+use ticker::{Executor, OutputSnapshot, SendTicker};
+use timetag::BlockClock;
+use traffic::{Direction, TrafficLog};
+use transport::{
+    PhaseLength, PhaseStream, TempoBroadcast, TransportBroadcast, TransportInfo, TransportMessage,
+};
+use watchdog::{Heartbeat, Watchdog};
+
+mod activity;
+mod allowlist;
+mod analysis;
+mod banks;
+mod changes;
+mod clock;
+mod curve;
+mod destination;
+mod dirty;
+mod discovery;
+mod editor;
+mod envelope;
+mod gate;
+mod levels;
+mod link;
+mod loudness;
+mod mapping;
+mod metronome;
+mod midi;
+mod migration;
+mod onset;
+mod open_stage_control;
+mod oscquery;
+mod pitch;
+mod profile;
+mod receiver;
+mod remote_config;
+mod resolver;
+mod routing;
+mod smoothing;
+mod snapshot;
+mod socket;
+mod status;
+mod stereo;
+mod sync;
+mod ticker;
+mod timetag;
+mod touchosc;
+mod traffic;
+mod transport;
+mod watchdog;
+
+/// The number of channels in `array_params`. Hosts need a fixed parameter list, so this is the
+/// most channels an instance can use, and `channel_count` decides how many of them are active.
+const NUM_CHANNELS: usize = 512;
+/// The number of active channels for new instances.
+const DEFAULT_CHANNEL_COUNT: u32 = 64;
+const DEFAULT_CHANNEL_VALUE: f32 = 0.0;
+/// The longest ramp between two changes when interpolating. Changes further apart than this are
+/// treated as the start of a new movement.
+const MAX_INTERPOLATION_MS: f32 = 250.0;
+
 struct SpaceRadio {
     params: Arc<SpaceRadioParams>,
-    sender: Arc<Mutex<Option<Sender>>>,
-    dirty_params: Arc<DashSet<usize>>,
+    sender: Arc<Mutex<Option<OscSocket>>>,
+    /// Destination hostnames are resolved by `_resolver` and sent to using these addresses.
+    addresses: Arc<ResolvedAddresses>,
+    dirty_params: Arc<DirtyFlags>,
+    /// The values the channels moved through since the last block, while every change is sent.
+    change_log: Arc<ChangeLog>,
+    /// Parameters in the integer, toggle, and scene banks that changed since the last block.
+    dirty_banks: Arc<DirtyFlags>,
+    /// Channels that should be ramped back to their home positions during the next block.
+    home_requests: Arc<DirtyFlags>,
+    /// Channels whose home fade finished, so the task executor can move their parameters there.
+    homes_reached: Arc<DirtyFlags>,
+    /// Channel values received over OSC by `_receiver`.
+    remote_values: Arc<RemoteValues>,
+    /// Lets `_receiver` record received values as automation while the editor is open, and lets
+    /// the task executor move parameters to their home positions.
+    automation: Arc<AutomationTarget>,
+    /// Resolves conflicting edits between instances that mirror each other's channels.
+    sync: Arc<SyncState>,
+    /// What each channel last sent, shown in the editor.
+    activity: Arc<ChannelActivity>,
+    /// Recent incoming and outgoing messages for the editor's traffic console.
+    traffic: Arc<TrafficLog>,
+    /// Socket errors, shown in the editor.
+    status: Arc<ConnectionStatus>,
+    /// Set by the Send All parameter, every channel is resent during the next block.
+    send_all_requested: Arc<AtomicBool>,
+    /// The channels' latest values for `_ticker`, used instead of sending from the audio thread
+    /// while `send_tick_ms` is set.
+    output_snapshot: Arc<OutputSnapshot>,
+    /// Handed to `_ticker` once the wrapper asks for the task executor.
+    tick_executor: Arc<Mutex<Option<Executor>>>,
+    /// Whether `send_tick_ms` was set during this block.
+    ticking: bool,
+    home_fade: Fade,
+    /// The latest value for each channel. This has been handed to the background task unless the
+    /// channel is marked as `pending`.
+    output_values: Vec<f32>,
+    sample_rate: f32,
+    /// The number of samples processed since the plugin was initialized.
+    sample_counter: u64,
+    /// The host's tempo during the current block, if it reports one.
+    tempo: Option<f64>,
+    /// Whether the host's transport was playing during the previous block.
+    was_playing: bool,
+    /// Set while `only_while_playing` is on and the transport is stopped. Channels hold their
+    /// changes until playback starts.
+    holding: bool,
+    /// Incremented for every block that sends anything. All messages sent during a block share the
+    /// same frame ID.
+    frame: u32,
+    sent_this_block: bool,
+    block_clock: BlockClock,
+    gate: InputGate,
+    level_meter: LevelMeter,
+    loudness_meter: LoudnessMeter,
+    stereo_meter: StereoMeter,
+    envelope: EnvelopeFollower,
+    /// The input is copied here for `_analyzer`, which stores what it finds in `analysis`.
+    analysis_input: AnalysisInput,
+    analysis: Arc<AnalysisResults>,
+    /// Each channel's config as far as the audio thread needs it, copied so it never has to lock.
+    channel_settings: Vec<ChannelSettings>,
+    /// Ramps for the channels with smoothing enabled.
+    smoothers: ChannelSmoothers,
+    /// A copy of `interpolate` from the parameters, updated every block.
+    interpolate: bool,
+    /// The value of `sample_counter` when each channel was last moved, for sizing its ramp when
+    /// interpolating.
+    last_change_sample: Vec<Option<u64>>,
+    /// Channels with an output value that's being held back, by muting, by the input gate, or by
+    /// the channel's minimum send interval.
+    pending: Vec<bool>,
+    /// The value of `sample_counter` when each channel last sent something.
+    last_sent_sample: Vec<Option<u64>>,
+    /// The output value each channel last sent.
+    last_sent_values: Vec<Option<f32>>,
+    /// A copy of `echo_cooldown_ms` from the parameters, updated every block.
+    echo_cooldown_ms: u32,
+    /// A copy of the number of active channels, updated every block.
+    channel_count: usize,
+    /// The token bucket for the global message rate cap. Every channel update takes one token.
+    rate_tokens: f64,
+    /// Pending channels are flushed starting from this index so every channel gets its turn when
+    /// the rate cap is hit.
+    round_robin: usize,
+    /// Preallocated storage for the channel indices drained from `dirty_params`, or picked to be
+    /// sent by `flush_pending()`.
+    scratch_indices: Vec<usize>,
+    metronome: Metronome,
+    clock: Clock,
+    transport: TransportBroadcast,
+    tempo_broadcast: TempoBroadcast,
+    phase_stream: PhaseStream,
+    link_clock: LinkClock,
+    mpe: MpeTracker,
+    /// Samples since all channels were last resent.
+    resync_elapsed: u64,
+    /// Samples since the last `/heartbeat` message.
+    keepalive_elapsed: u64,
+    keepalive_count: u32,
+    heartbeat: Arc<Heartbeat>,
+    _watchdog: Watchdog,
+    _resolver: Resolver,
+    _oscquery: OscQueryServer,
+    /// Advertises the OSC socket and finds other OSC services on the network.
+    discovery: Discovery,
+    _receiver: OscReceiver,
+    link: LinkSession,
+    _analyzer: Analyzer,
+    _ticker: SendTicker,
+    /// MIDI messages received over OSC by `_receiver`, to be sent to the plugin's MIDI output.
+    midi_output: mpsc::Receiver<MidiMessage>,
 }
 
 /// The [`Params`] derive macro gathers all of the information needed for the wrapper to know about
@@ -21,10 +230,300 @@ struct SpaceRadio {
 struct SpaceRadioParams {
     #[nested(array, group = "Array Parameters")]
     pub array_params: Vec<ArrayParams>,
+    #[nested(array, group = "Integers")]
+    pub int_params: Vec<IntParams>,
+    #[nested(array, group = "Toggles")]
+    pub toggle_params: Vec<ToggleParams>,
+    #[nested(array, group = "Scenes")]
+    pub scene_params: Vec<SceneParams>,
+    #[nested(array, group = "XY Pads")]
+    pub xy_params: Vec<XyParams>,
+    /// The editor's size, the editor itself is in the `editor` module.
+    #[persist = "editor_state"]
+    editor_state: Arc<EguiState>,
+    /// Every packet is sent to all enabled destinations.
+    #[persist = "destinations"]
+    destinations: RwLock<Vec<Destination>>,
+    /// Prepended to every outgoing address, e.g. `/spaceradio/1`.
+    #[persist = "osc_namespace"]
+    osc_namespace: RwLock<String>,
+    /// How long the destination fields need to stay unchanged before messages are sent to the new
+    /// destination.
+    #[persist = "retarget_debounce"]
+    retarget_debounce_ms: RwLock<u32>,
+    /// The local interface and source port the OSC socket binds to. Changes rebind the socket.
+    #[persist = "local_bind"]
+    local_bind: RwLock<LocalBind>,
+    /// Serve the channel namespace over OSCQuery so clients can discover the addresses.
+    #[persist = "oscquery_enabled"]
+    oscquery_enabled: RwLock<bool>,
+    /// The OSCQuery server's HTTP port. Zero lets the OS pick one, which is then logged.
+    #[persist = "oscquery_port"]
+    oscquery_port: RwLock<u16>,
+    /// Advertise the OSC socket as an `_osc._udp` service over mDNS.
+    #[persist = "advertise_mdns"]
+    advertise_mdns: RwLock<bool>,
+    /// The UDP port incoming OSC messages that set channels are received on. Zero disables
+    /// receiving.
+    #[persist = "receive_port"]
+    receive_port: RwLock<u16>,
+    /// An IPv4 multicast group the receive socket joins, so every machine in a room can listen to
+    /// the same sender.
+    #[persist = "receive_group"]
+    receive_group: RwLock<Option<Ipv4Addr>>,
+    /// Write received values to the host's parameters so they can be recorded as automation. This
+    /// only works while the editor is open.
+    #[persist = "write_automation"]
+    write_automation: RwLock<bool>,
+    /// Changes to a channel aren't sent for this long after it received a value over OSC, so
+    /// linked endpoints don't bounce values back and forth. Zero disables this.
+    #[persist = "echo_cooldown"]
+    echo_cooldown_ms: RwLock<u32>,
+    /// Other Space Radio instances to keep this instance's channels mirrored with. Each instance
+    /// lists the others here and receives their changes on its receive port.
+    #[persist = "sync_peers"]
+    sync_peers: RwLock<Vec<Destination>>,
+    /// The hosts allowed to send OSC messages to the receive port, as addresses or CIDR ranges.
+    /// Messages from anywhere else are dropped. An empty list allows everyone.
+    #[persist = "receive_allowlist"]
+    receive_allowlist: RwLock<Vec<String>>,
+    /// Accept `/config/...` messages on the receive port that change these settings.
+    #[persist = "accept_config"]
+    accept_config: RwLock<bool>,
+    /// Accept messages to `{namespace}/all` on the receive port, which set every channel in use at
+    /// once.
+    #[persist = "accept_all"]
+    accept_all: RwLock<bool>,
+    /// Forward messages received on the receive port that don't control the plugin to the
+    /// destinations, so the plugin can act as an OSC relay.
+    #[persist = "pass_through"]
+    pass_through: RwLock<bool>,
+    /// The channel that gets bound to the address of the next incoming OSC message or the next
+    /// incoming MIDI CC, whichever comes first.
+    learn_channel: Mutex<Option<usize>>,
+    /// Incremented when a setting that goes into the channels' addresses changes, so the task
+    /// executor and the receiver rebuild what they precomputed from them, see
+    /// [`Self::invalidate_addresses()`].
+    address_generation: AtomicU64,
+    /// Set between `initialize()` and `deactivate()`. The sockets are only open while the plugin
+    /// is active, so a deactivated or bypassed instance doesn't keep its ports bound.
+    active: AtomicBool,
+    /// How many of the channels are in use. The others don't send or receive anything, and are
+    /// hidden from the editor and OSCQuery.
+    #[persist = "channel_count"]
+    channel_count: RwLock<u32>,
+    /// Per-channel settings that aren't exposed as parameters.
+    #[persist = "channels"]
+    channels: RwLock<Vec<ChannelConfig>>,
+    /// Append the block's frame ID as an extra int argument to every message so receivers can
+    /// reassemble frames that arrive out of order over UDP.
+    #[persist = "frame_ids"]
+    stamp_frame_ids: RwLock<bool>,
+    /// Send each block's channel updates as a single timetagged bundle instead of one message per
+    /// channel.
+    #[persist = "bundle_frames"]
+    bundle_frames: RwLock<bool>,
+    /// Send every value a channel moved through during a block instead of only the last one. The
+    /// block's changes always go out as a single timetagged bundle then.
+    #[persist = "send_every_change"]
+    send_every_change: RwLock<bool>,
+    /// Added to the bundles' timetags to account for the audio interface's output latency, in
+    /// milliseconds.
+    #[persist = "timetag_latency"]
+    timetag_latency_ms: RwLock<f32>,
+    /// Append the number of seconds since the channel's previous message so receivers know how
+    /// long to interpolate over.
+    #[persist = "include_interval"]
+    include_interval: RwLock<bool>,
+    /// How long queued background tasks may go unhandled before the socket is rebuilt. Zero
+    /// disables the watchdog.
+    #[persist = "watchdog_timeout"]
+    watchdog_timeout_ms: RwLock<u32>,
+    /// How often a `/heartbeat` message is sent so receivers and dashboards can tell the plugin is
+    /// alive, even when no channels are moving. Zero disables it.
+    #[persist = "keepalive_interval"]
+    keepalive_interval_ms: RwLock<u32>,
+    /// How often every channel's value is resent, so receivers that joined late or dropped packets
+    /// catch up on their own. Zero disables it.
+    #[persist = "resync_interval"]
+    resync_interval_ms: RwLock<u32>,
+    /// Send channel updates from a separate thread at this interval instead of at the end of every
+    /// block, so the network traffic is steady regardless of the host's buffer size. Zero sends
+    /// from the audio thread.
+    #[persist = "send_tick"]
+    send_tick_ms: RwLock<u32>,
+    /// Hold back channel updates while the host's transport is stopped, except for Send All, so
+    /// editing a session that's wired to live equipment doesn't move anything.
+    #[persist = "only_while_playing"]
+    only_while_playing: RwLock<bool>,
+    /// Send every channel's value when the host's transport starts playing.
+    #[persist = "snapshot_on_play"]
+    snapshot_on_play: RwLock<bool>,
+    /// Also forward the expression of MPE controllers per note under `/mpe/<note ID>/...`.
+    #[persist = "mpe"]
+    mpe: RwLock<bool>,
+    /// Send the input's RMS and peak levels under `/level/...`.
+    #[persist = "levels"]
+    levels_enabled: RwLock<bool>,
+    /// How often the levels are sent. Zero sends them every block.
+    #[persist = "levels_interval"]
+    levels_interval_ms: RwLock<u32>,
+    /// Whether the levels, envelopes, input gate, and analyses listen to the main or the sidechain
+    /// input.
+    #[persist = "analysis_source"]
+    analysis_source: RwLock<AnalysisSource>,
+    /// How often the envelope follower's output is sent. Zero sends it every block.
+    #[persist = "envelope_interval"]
+    envelope_interval_ms: RwLock<u32>,
+    /// Send the input's EBU R128 loudness under `/loudness/...`.
+    #[persist = "loudness"]
+    loudness_enabled: RwLock<bool>,
+    /// Send the input's stereo correlation, width, and balance under `/stereo/...`.
+    #[persist = "stereo"]
+    stereo_enabled: RwLock<bool>,
+    /// How often the stereo image is sent. Zero sends it every block.
+    #[persist = "stereo_interval"]
+    stereo_interval_ms: RwLock<u32>,
+    /// Send downsampled chunks of the input's waveform as `/waveform` blobs.
+    #[persist = "waveform"]
+    waveform_enabled: RwLock<bool>,
+    /// The number of points in each chunk.
+    #[persist = "waveform_points"]
+    waveform_points: RwLock<u32>,
+    /// How much of the input each chunk covers, which is also how often they're sent. Zero sends
+    /// the input as is, without downsampling.
+    #[persist = "waveform_interval"]
+    waveform_interval_ms: RwLock<u32>,
+    /// Send the energy in `spectrum_bands` frequency bands as `/spectrum`.
+    #[persist = "spectrum"]
+    spectrum_enabled: RwLock<bool>,
+    #[persist = "spectrum_bands"]
+    spectrum_bands: RwLock<u32>,
+    /// How often the spectrum is sent. Zero sends it as often as it's analyzed.
+    #[persist = "spectrum_interval"]
+    spectrum_interval_ms: RwLock<u32>,
+    /// Track the input's pitch and send it under `/pitch/...`.
+    #[persist = "pitch"]
+    pitch_enabled: RwLock<bool>,
+    /// How often the pitch is sent. Zero sends it as often as it's analyzed.
+    #[persist = "pitch_interval"]
+    pitch_interval_ms: RwLock<u32>,
+    /// Send `/onset <strength>` whenever an onset is detected in the input.
+    #[persist = "onset"]
+    onset_enabled: RwLock<bool>,
+    /// How far the spectral flux needs to rise above its recent average to count as an onset.
+    #[persist = "onset_sensitivity"]
+    onset_sensitivity: RwLock<f32>,
+    /// Send the spectral centroid, flatness, and rolloff under `/spectral/...`.
+    #[persist = "features"]
+    features_enabled: RwLock<bool>,
+    /// How often the spectral features are sent. Zero sends them as often as they're analyzed.
+    #[persist = "features_interval"]
+    features_interval_ms: RwLock<u32>,
+    /// Broadcast the host's transport state and position under `/transport/...`.
+    #[persist = "transport"]
+    transport_enabled: RwLock<bool>,
+    /// How often the position is sent while the transport is playing. Zero sends it every block.
+    #[persist = "transport_interval"]
+    transport_interval_ms: RwLock<u32>,
+    /// Send `/tempo` whenever the host's tempo changes.
+    #[persist = "tempo"]
+    tempo_enabled: RwLock<bool>,
+    /// Also send the tempo this often when it doesn't change. Zero only sends changes.
+    #[persist = "tempo_interval"]
+    tempo_interval_ms: RwLock<u32>,
+    /// Stream the position within the current bar or beats as `/phase` while playing.
+    #[persist = "phase"]
+    phase_enabled: RwLock<bool>,
+    #[persist = "phase_length"]
+    phase_length: RwLock<PhaseLength>,
+    /// How often `/phase` is sent. Zero sends it every block.
+    #[persist = "phase_interval"]
+    phase_interval_ms: RwLock<u32>,
+    /// Join an Ableton Link session and broadcast its tempo and phase under `/link/...`.
+    #[persist = "link"]
+    link_enabled: RwLock<bool>,
+    /// The number of beats in a Link phase ramp.
+    #[persist = "link_quantum"]
+    link_quantum: RwLock<f64>,
+    /// How often `/link/phase` is sent. Zero sends it every block.
+    #[persist = "link_interval"]
+    link_interval_ms: RwLock<u32>,
+    /// Resync all channels at the start of every Link quantum instead of on the resync interval.
+    #[persist = "link_align_resync"]
+    link_align_resync: RwLock<bool>,
+    /// Channel values recalled by MIDI program changes.
+    #[persist = "snapshots"]
+    snapshots: RwLock<Vec<Snapshot>>,
+    /// Send the host's transport as `/clock/...` messages at MIDI clock resolution.
+    #[persist = "clock"]
+    clock_enabled: RwLock<bool>,
+    /// When enabled, channel indices in addresses are offset by `(instance_number - 1) *
+    /// channel_stride` so multiple instances can share a receiver on contiguous indices.
+    #[persist = "instance_offset"]
+    offset_by_instance: RwLock<bool>,
+    /// Starts at one.
+    #[persist = "instance_number"]
+    instance_number: RwLock<u32>,
+    #[persist = "channel_stride"]
+    channel_stride: RwLock<u32>,
+    /// Use the channels' names instead of their indices in the profile's addresses. Channels
+    /// without a name keep using their index.
+    #[persist = "names_in_addresses"]
+    names_in_addresses: RwLock<bool>,
+    /// Ramp channels without a smoothing time of their own from one change to the next, sending
+    /// the ramps at the smoothing rate. This turns sparse host automation into continuous movement
+    /// at the cost of one change's worth of latency.
+    #[persist = "interpolate"]
+    interpolate: RwLock<bool>,
+    #[persist = "non_finite_policy"]
+    non_finite_policy: RwLock<NonFinitePolicy>,
+    #[persist = "profiles"]
+    profiles: RwLock<Vec<OutputProfile>>,
+    /// The name of the profile in `profiles` that's used for sending.
+    #[persist = "active_profile"]
+    active_profile: RwLock<String>,
+    /// The format the state was saved in, see the `migration` module.
+    #[persist = "version"]
+    state_version: StateVersion,
+    /// The single destination from before there was a destination list. These are only set when
+    /// restoring an old state, until it's migrated.
     #[persist = "osc_address"]
-    osc_destination_address: RwLock<String>,
+    legacy_osc_address: RwLock<Option<String>>,
     #[persist = "osc_port"]
-    osc_destination_port: RwLock<u16>,
+    legacy_osc_port: RwLock<Option<u16>>,
+    #[persist = "protocol"]
+    legacy_protocol: RwLock<Option<Protocol>>,
+    #[persist = "tcp_framing"]
+    legacy_tcp_framing: RwLock<Option<Framing>>,
+
+    /// Ramps every channel to its home position when switched on.
+    #[id = "go_home"]
+    pub go_home: BoolParam,
+    #[id = "home_fade"]
+    pub home_fade_time: FloatParam,
+    /// Resends the current value of every channel when switched on, e.g. after the receiving
+    /// application was restarted.
+    #[id = "send_all"]
+    pub send_all: BoolParam,
+    /// Drops all outgoing OSC while switched on, as a kill switch the host can automate. Every
+    /// channel is resent when it's switched off again.
+    #[id = "output_mute"]
+    pub output_muted: BoolParam,
+    /// How often channels with smoothing enabled send their intermediate values.
+    #[id = "smoothing_rate"]
+    pub smoothing_rate: FloatParam,
+    /// A cap on the number of channel updates sent per second across all channels. Updates over
+    /// the cap are deferred, keeping only the latest value. Zero means unlimited.
+    #[id = "max_rate"]
+    pub max_message_rate: IntParam,
+
+    #[nested(group = "Input Gate")]
+    pub gate: GateParams,
+    #[nested(group = "Metronome")]
+    pub metronome: MetronomeParams,
+    #[nested(group = "Envelope Follower")]
+    pub envelope: EnvelopeParams,
 }
 
 #[derive(Params)]
@@ -35,71 +534,1264 @@ struct ArrayParams {
     pub val: FloatParam,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+struct ChannelConfig {
+    /// A name given to the channel in the editor.
+    name: Option<String>,
+    /// The group the channel belongs to, for the `{group}` placeholder in address templates.
+    group: Option<String>,
+    /// A fixed OSC address for this channel, like `/synth/filter/cutoff`. This replaces the
+    /// address from the output profile.
+    address: Option<String>,
+    /// The value this channel is ramped to when it's sent home.
+    home: f32,
+    /// When set, the normalized value is scaled to this range instead of the output profile's.
+    range: Option<ValueRange>,
+    /// Reshapes the normalized value before it's scaled.
+    curve: ResponseCurve,
+    /// Transmitted values are multiplied by `scale` and then have `offset` added to them, after
+    /// they've been scaled to the output range. A negative scale flips the channel's polarity.
+    scale: f32,
+    offset: f32,
+    /// When positive, transmitted values are snapped to multiples of this, like `1` for whole
+    /// numbers.
+    step: f32,
+    /// When set, the channel's value is treated as a linear gain and sent in decibels.
+    decibels: Option<DecibelRange>,
+    /// Overrides the output profile's type tag for this channel.
+    value_type: Option<ChannelValueType>,
+    /// Only transmit while there's audio on the plugin's input.
+    gated: bool,
+    /// Keeps the channel from transmitting while it still follows its parameter. The latest value
+    /// is sent once the channel is unmuted.
+    muted: bool,
+    /// When positive, changes are smoothed over this many milliseconds and the intermediate values
+    /// are sent at the smoothing rate.
+    smoothing_ms: f32,
+    /// The minimum time between two messages for this channel, in musical time.
+    send_interval: SendInterval,
+    /// The most messages per second this channel may send. Changes in between are coalesced into
+    /// the latest value. Zero means unlimited.
+    max_rate: f32,
+    /// The channel only transmits once its normalized value has moved by more than this since its
+    /// last message.
+    min_delta: f32,
+    /// An incoming OSC address bound to this channel through OSC learn, in addition to the
+    /// channel's own address.
+    input_address: Option<String>,
+    /// A MIDI CC bound to this channel through MIDI learn, which then sets the channel.
+    midi_cc: Option<MidiCc>,
+    /// Where the channel is sent to. Without any routes, the channel's own address is sent to
+    /// every destination.
+    routes: Vec<Route>,
+}
+
+impl Default for ChannelConfig {
+    fn default() -> Self {
+        Self {
+            name: None,
+            group: None,
+            address: None,
+            home: DEFAULT_CHANNEL_VALUE,
+            range: None,
+            curve: ResponseCurve::Linear,
+            scale: 1.0,
+            offset: 0.0,
+            step: 0.0,
+            decibels: None,
+            value_type: None,
+            gated: false,
+            muted: false,
+            smoothing_ms: 0.0,
+            send_interval: SendInterval::Free,
+            max_rate: 0.0,
+            min_delta: 0.0,
+            input_address: None,
+            midi_cc: None,
+            routes: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+enum SendInterval {
+    /// Send every change.
+    #[default]
+    Free,
+    Whole,
+    Half,
+    Quarter,
+    Eighth,
+    Sixteenth,
+    ThirtySecond,
+}
+
+impl SendInterval {
+    /// The interval's length in quarter note beats.
+    fn beats(self) -> Option<f64> {
+        match self {
+            SendInterval::Free => None,
+            SendInterval::Whole => Some(4.0),
+            SendInterval::Half => Some(2.0),
+            SendInterval::Quarter => Some(1.0),
+            SendInterval::Eighth => Some(0.5),
+            SendInterval::Sixteenth => Some(0.25),
+            SendInterval::ThirtySecond => Some(0.125),
+        }
+    }
+}
+
+/// Text typed in by the user as it's used in addresses, with the characters OSC reserves for
+/// address patterns replaced by underscores. OSC addresses are printable ASCII, so whitespace,
+/// control characters, and anything outside of ASCII are replaced too. Empty text isn't used.
+fn address_segment(text: &str) -> Option<String> {
+    let text = text.trim();
+    (!text.is_empty()).then(|| {
+        text.chars()
+            .map(|c| match c {
+                '#' | '*' | ',' | '/' | '?' | '[' | ']' | '{' | '}' => '_',
+                c if !c.is_ascii_graphic() => '_',
+                c => c,
+            })
+            .collect()
+    })
+}
+
+/// The namespace the way it's stored, with a leading slash and without a trailing one so addresses
+/// can be appended to it as is. An empty namespace stays empty.
+fn normalize_namespace(namespace: &str) -> String {
+    let namespace = namespace.trim().trim_matches('/');
+    if namespace.is_empty() {
+        String::new()
+    } else {
+        format!("/{namespace}")
+    }
+}
+
+/// The number of channels before `index` that are in the same group, or that are also not in a
+/// group.
+fn group_member(index: usize, channels: &[ChannelConfig]) -> usize {
+    let group = channels.get(index).and_then(ChannelConfig::group_name);
+    channels
+        .iter()
+        .take(index)
+        .filter(|channel| channel.group_name() == group)
+        .count()
+}
+
+/// The indices of the first `count` channels by group, in the order the groups first appear in.
+/// Channels that aren't in a group are listed under `None`.
+fn channel_groups(channels: &[ChannelConfig], count: usize) -> Vec<(Option<&str>, Vec<usize>)> {
+    let mut groups: Vec<(Option<&str>, Vec<usize>)> = Vec::new();
+    for (index, channel) in channels.iter().enumerate().take(count) {
+        let group = channel.group_name();
+        match groups.iter_mut().find(|(name, _)| *name == group) {
+            Some((_, members)) => members.push(index),
+            None => groups.push((group, vec![index])),
+        }
+    }
+
+    groups
+}
+
+/// The parts of a [`ChannelConfig`] that are used on the audio thread.
+#[derive(Debug, Clone, Copy, Default)]
+struct ChannelSettings {
+    gated: bool,
+    muted: bool,
+    smoothing_ms: f32,
+    send_interval: SendInterval,
+    max_rate: f32,
+    min_delta: f32,
+    midi_cc: Option<MidiCc>,
+}
+
+impl From<&ChannelConfig> for ChannelSettings {
+    fn from(config: &ChannelConfig) -> Self {
+        Self {
+            gated: config.gated,
+            muted: config.muted,
+            smoothing_ms: config.smoothing_ms,
+            send_interval: config.send_interval,
+            max_rate: config.max_rate,
+            min_delta: config.min_delta,
+            midi_cc: config.midi_cc,
+        }
+    }
+}
+
+impl ChannelConfig {
+    /// Convert a channel's parameter value to the value that gets transmitted. The response curve
+    /// is applied first, then the value is scaled to the output range, then the channel's scale
+    /// and offset are applied, and finally the step size. Decibel values are absolute, and
+    /// channels with their own type tag or range use that instead of the profile's range.
+    fn transform(&self, value: f32, profile: &OutputProfile) -> f32 {
+        let value = self.curve.apply(value);
+        let value = match (&self.decibels, &self.value_type) {
+            (Some(range), _) => range.gain_to_db(value),
+            (None, Some(ChannelValueType::Int { min, max })) => {
+                *min as f32 + (*max as f32 - *min as f32) * value
+            }
+            (None, Some(ChannelValueType::Bool { .. })) => value,
+            (None, Some(ChannelValueType::Float) | None) => match &self.range {
+                Some(range) => range.scale(value),
+                None => profile.scale(value),
+            },
+        };
+        let value = value * self.scale + self.offset;
+
+        if self.step > 0.0 {
+            (value / self.step).round() * self.step
+        } else {
+            value
+        }
+    }
+
+    /// The range float values are scaled to, before they're sorted. This is the channel's own
+    /// range if it has one.
+    fn float_range(&self, profile: &OutputProfile) -> (f32, f32) {
+        match &self.range {
+            Some(range) => (range.min, range.max),
+            None => (profile.min, profile.max),
+        }
+    }
+
+    /// The channel's name as it's used in addresses, see [`address_segment()`].
+    fn address_name(&self) -> Option<String> {
+        address_segment(self.name.as_deref()?)
+    }
+
+    /// The channel's group as it's used in addresses, see [`address_segment()`].
+    fn address_group(&self) -> Option<String> {
+        address_segment(self.group.as_deref()?)
+    }
+
+    /// The group as it was typed in, if the channel is in one.
+    fn group_name(&self) -> Option<&str> {
+        self.group
+            .as_deref()
+            .map(str::trim)
+            .filter(|group| !group.is_empty())
+    }
+
+    /// The inverse of [`transform()`][Self::transform()], used for values received over OSC.
+    fn normalize(&self, value: f32, profile: &OutputProfile) -> f32 {
+        if self.scale == 0.0 {
+            return 0.0;
+        }
+
+        let value = (value - self.offset) / self.scale;
+        let normalized = match (&self.decibels, &self.value_type) {
+            (Some(range), _) => range.db_to_gain(value),
+            (None, Some(ChannelValueType::Int { min, max })) if min != max => {
+                (value - *min as f32) / (*max as f32 - *min as f32)
+            }
+            (None, Some(ChannelValueType::Int { .. })) => 0.0,
+            (None, Some(ChannelValueType::Bool { .. })) => value,
+            (None, Some(ChannelValueType::Float) | None) => {
+                let (min, max) = self.float_range(profile);
+                if min != max {
+                    (value - min) / (max - min)
+                } else {
+                    0.0
+                }
+            }
+        };
+
+        self.curve.invert(normalized)
+    }
+
+    /// The lower and upper bounds of what [`transform()`][Self::transform()] produces.
+    fn output_range(&self, profile: &OutputProfile) -> (f32, f32) {
+        let (min, max) = match (&self.decibels, &self.value_type) {
+            (Some(range), _) => (range.silence.min(range.min), range.max),
+            (None, Some(ChannelValueType::Int { min, max })) => {
+                (*min.min(max) as f32, *min.max(max) as f32)
+            }
+            (None, Some(ChannelValueType::Bool { .. })) => (0.0, 1.0),
+            (None, Some(ChannelValueType::Float) | None) => self.float_range(profile),
+        };
+
+        let (min, max) = (
+            min * self.scale + self.offset,
+            max * self.scale + self.offset,
+        );
+        (min.min(max), min.max(max))
+    }
+
+    /// Encode a transformed value using the channel's type tag, or the profile's if it doesn't
+    /// have one.
+    fn encode(&self, value: f32, profile: &OutputProfile) -> osc::Type {
+        match &self.value_type {
+            Some(ChannelValueType::Float) => ValueType::Float.encode(value),
+            Some(ChannelValueType::Int { .. }) => ValueType::Int.encode(value),
+            Some(ChannelValueType::Bool { threshold }) => osc::Type::Bool(value >= *threshold),
+            None => profile.value_type.encode(value),
+        }
+    }
+}
+
+/// A per-channel override for the type of the transmitted argument.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum ChannelValueType {
+    Float,
+    /// The normalized value is scaled to `[min, max]` and rounded.
+    Int {
+        min: i32,
+        max: i32,
+    },
+    /// True when the transformed value is at or above the threshold.
+    Bool {
+        threshold: f32,
+    },
+}
+
+/// What to do with a transmitted value that turned out to be NaN or infinite.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum NonFinitePolicy {
+    /// Clamp infinities to the channel's output range. NaN becomes the lower bound.
+    Clamp,
+    /// Repeat the last finite value sent for that channel, or skip the send if there isn't one.
+    LastGood,
+    Skip,
+}
+
+/// Bookkeeping for the task executor.
+struct SendState {
+    /// The last finite value transmitted for each channel.
+    last_good: Vec<Option<f32>>,
+    non_finite_count: u64,
+    last_sent: Vec<Option<Instant>>,
+    /// The current frame's messages when bundling is enabled, grouped by where the frame came
+    /// from and the destination they're routed to. An empty target means all destinations.
+    bundles: Vec<(FrameSource, String, Vec<osc::Packet>)>,
+    /// Each channel's address including the namespace, built the first time the channel sends
+    /// something after the addresses have changed.
+    addresses: Vec<Option<String>>,
+    /// The active output profile, which is only looked up again when the addresses change.
+    profile: Arc<OutputProfile>,
+    /// The address generation `addresses` and `profile` belong to.
+    generation: Option<u64>,
+}
+
+impl SendState {
+    fn new(num_channels: usize) -> Self {
+        Self {
+            last_good: vec![None; num_channels],
+            non_finite_count: 0,
+            last_sent: vec![None; num_channels],
+            bundles: Vec::new(),
+            addresses: vec![None; num_channels],
+            profile: Arc::default(),
+            generation: None,
+        }
+    }
+
+    /// Look up the active profile again and forget the precomputed addresses if the addresses
+    /// changed since the last call.
+    fn refresh(&mut self, params: &SpaceRadioParams) {
+        let generation = params.address_generation();
+        if self.generation != Some(generation) {
+            self.generation = Some(generation);
+            self.profile = Arc::new(params.active_profile());
+            self.addresses.fill(None);
+        }
+    }
+
+    /// The channel's precomputed address, calling `build` if it hasn't been built yet.
+    fn address(&mut self, index: usize, build: impl FnOnce() -> String) -> &str {
+        self.addresses[index].get_or_insert_with(build)
+    }
+
+    /// Add a message to the current frame's bundle for a route's destination.
+    fn bundle(&mut self, source: FrameSource, target: &str, packet: osc::Packet) {
+        match self
+            .bundles
+            .iter_mut()
+            .find(|(bundle_source, bundle_target, _)| {
+                *bundle_source == source && bundle_target == target
+            }) {
+            Some((_, _, content)) => content.push(packet),
+            None => self.bundles.push((source, target.to_owned(), vec![packet])),
+        }
+    }
+
+    /// Take the current frame's bundles, leaving those of frames from elsewhere alone.
+    fn take_bundles(&mut self, source: FrameSource) -> Vec<(String, Vec<osc::Packet>)> {
+        let (taken, kept) = std::mem::take(&mut self.bundles)
+            .into_iter()
+            .partition(|(bundle_source, _, _)| *bundle_source == source);
+        self.bundles = kept;
+        taken
+            .into_iter()
+            .map(|(_, target, content)| (target, content))
+            .collect()
+    }
+
+    /// Record that a message is being sent for `index` and return the number of seconds since the
+    /// previous one, or zero for the first message.
+    fn interval(&mut self, index: usize) -> f32 {
+        let now = Instant::now();
+        let previous = self.last_sent[index].replace(now);
+        previous.map_or(0.0, |previous| (now - previous).as_secs_f32())
+    }
+
+    /// Apply `policy` to a transmitted value, returning `None` if the send should be skipped.
+    fn sanitize(
+        &mut self,
+        index: usize,
+        value: f32,
+        policy: NonFinitePolicy,
+        (min, max): (f32, f32),
+    ) -> Option<f32> {
+        if value.is_finite() {
+            self.last_good[index] = Some(value);
+            return Some(value);
+        }
+
+        // Something upstream is producing garbage, so there's no need to spam the log with it
+        self.non_finite_count += 1;
+        if self.non_finite_count == 1 || self.non_finite_count % 1000 == 0 {
+            nih_warn!(
+                "Channel {index} produced a non-finite value ({value}), {} so far",
+                self.non_finite_count
+            );
+        }
+
+        match policy {
+            NonFinitePolicy::Clamp if value == f32::INFINITY => Some(max),
+            NonFinitePolicy::Clamp => Some(min),
+            NonFinitePolicy::LastGood => self.last_good[index],
+            NonFinitePolicy::Skip => None,
+        }
+    }
+}
+
+/// A channel's own output range. `min` is sent for a normalized value of zero and `max` for one, so
+/// the range can be inverted by making `min` the larger of the two.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct ValueRange {
+    min: f32,
+    max: f32,
+}
+
+impl ValueRange {
+    fn scale(&self, value: f32) -> f32 {
+        self.min + (self.max - self.min) * value
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+struct DecibelRange {
+    min: f32,
+    max: f32,
+    /// Sent for a gain of zero. This can't be negative infinity since the state is stored as JSON.
+    silence: f32,
+}
+
+impl Default for DecibelRange {
+    fn default() -> Self {
+        Self {
+            min: -60.0,
+            max: 0.0,
+            silence: -90.0,
+        }
+    }
+}
+
+impl DecibelRange {
+    fn gain_to_db(&self, gain: f32) -> f32 {
+        if gain <= 0.0 {
+            self.silence
+        } else {
+            (20.0 * gain.log10()).clamp(self.min, self.max)
+        }
+    }
+
+    /// The inverse of [`gain_to_db()`][Self::gain_to_db()].
+    fn db_to_gain(&self, db: f32) -> f32 {
+        if db < self.min || db <= self.silence {
+            0.0
+        } else {
+            util::db_to_gain(db.clamp(self.min, self.max))
+        }
+    }
+}
+
+/// A linear ramp of the transmitted values from wherever the channels currently are to a set of
+/// targets. Channels drop out of the fade as soon as their parameter gets moved.
+struct Fade {
+    from: Vec<f32>,
+    to: Vec<f32>,
+    active: Vec<bool>,
+    position: u32,
+    length: u32,
+}
+
+impl Fade {
+    fn new(num_channels: usize) -> Self {
+        Self {
+            from: vec![0.0; num_channels],
+            to: vec![0.0; num_channels],
+            active: vec![false; num_channels],
+            position: 0,
+            length: 0,
+        }
+    }
+
+    fn is_running(&self) -> bool {
+        self.active.iter().any(|active| *active)
+    }
+
+    /// Advance the fade by `samples` samples and return the progress in `[0, 1]`.
+    fn advance(&mut self, samples: u32) -> f32 {
+        self.position = (self.position + samples).min(self.length);
+        if self.length == 0 {
+            1.0
+        } else {
+            self.position as f32 / self.length as f32
+        }
+    }
+
+    fn value(&self, index: usize, progress: f32) -> f32 {
+        self.from[index] + (self.to[index] - self.from[index]) * progress
+    }
+}
+
 impl SpaceRadio {
-    fn setup_sender(&mut self) {
-        let (tx_sender, rx_sender) = mpsc::channel();
+    /// Set a channel's output value and mark it to be sent at the end of the block, unless the
+    /// value hasn't moved enough to be worth sending. A channel that changes several times during
+    /// a block only sends the value it ends up at.
+    fn queue_update(&mut self, index: usize, value: f32) {
+        self.output_values[index] = value;
+
+        // Values that were just received over OSC aren't echoed back, or two linked endpoints
+        // would keep sending the same value back and forth
+        if self.echo_cooldown_ms > 0
+            && self
+                .remote_values
+                .received_within(index, Duration::from_millis(self.echo_cooldown_ms as u64))
+        {
+            self.pending[index] = false;
+            self.last_sent_values[index] = Some(value);
+            return;
+        }
+
+        let min_delta = self.channel_settings[index].min_delta;
+        if let Some(last_sent_value) = self.last_sent_values[index] {
+            if min_delta > 0.0 && (value - last_sent_value).abs() <= min_delta {
+                // A value that was held back may have moved back to where it was
+                self.pending[index] = false;
+                return;
+            }
+        }
 
-        thread::spawn(move || {
-            let sender = Arc::new(Mutex::new(Some(
-                osc::sender().expect("Could not bind to default socket"), // .connect(target_addr.clone())
-                                                                          // .expect("Could not connect to socket at address"),
-            )));
+        self.pending[index] = true;
+    }
 
-            tx_sender.send(sender).unwrap();
+    /// Send the latest value of every channel that's waiting to send and is allowed to. Channels
+    /// are always sent in ascending order since receivers may care about the sequence, like an X
+    /// coordinate arriving before its Y coordinate. They're picked round-robin though, so a global
+    /// rate cap doesn't always favor the first channels.
+    fn flush_pending(&mut self, context: &mut impl ProcessContext<Self>) {
+        let rate_capped = self.rate_capped();
+        let mut ready = std::mem::take(&mut self.scratch_indices);
+        ready.clear();
+        for offset in 0..NUM_CHANNELS {
+            let index = (self.round_robin + offset) % NUM_CHANNELS;
+            if self.pending[index] && self.can_send(index) {
+                // The token is only reserved here, sending the value takes it
+                ready.push(index);
+                if rate_capped {
+                    self.rate_tokens -= 1.0;
+                }
+                self.round_robin = (index + 1) % NUM_CHANNELS;
+            }
+        }
+
+        if rate_capped {
+            self.rate_tokens += ready.len() as f64;
+        }
+        ready.sort_unstable();
+        for &index in &ready {
+            self.send_output_value(context, index);
+        }
+        self.scratch_indices = ready;
+    }
+
+    fn send_output_value(&mut self, context: &mut impl ProcessContext<Self>, index: usize) {
+        if self.ticking {
+            self.output_snapshot.store(index, self.output_values[index]);
+        } else {
+            context.execute_background(BackgroundTask::UpdateParameter {
+                index,
+                value: self.output_values[index],
+                frame: self.frame,
+                source: FrameSource::Block,
+            });
+            self.heartbeat.task_queued();
+            self.sent_this_block = true;
+        }
+        self.pending[index] = false;
+        self.last_sent_sample[index] = Some(self.sample_counter);
+        self.last_sent_values[index] = Some(self.output_values[index]);
+        if self.rate_capped() {
+            self.rate_tokens -= 1.0;
+        }
+    }
+
+    /// Send the values the channels' parameters moved through before their latest one, when every
+    /// change is sent. These go out as they are ahead of the block's regular updates, without
+    /// smoothing or rate limits, but muted, gated, and held channels still skip them.
+    fn send_superseded_changes(&mut self, context: &mut impl ProcessContext<Self>) {
+        let change_log = Arc::clone(&self.change_log);
+        change_log.drain_superseded(|index, value| {
+            let settings = &self.channel_settings[index];
+            if self.ticking
+                || self.holding
+                || index >= self.channel_count
+                || settings.muted
+                || (settings.gated && !self.gate.is_open())
+            {
+                return;
+            }
+
+            context.execute_background(BackgroundTask::UpdateParameter {
+                index,
+                value,
+                frame: self.frame,
+                source: FrameSource::Block,
+            });
+            self.heartbeat.task_queued();
+            self.sent_this_block = true;
+        });
+    }
+
+    /// Whether the global rate cap is enabled. The token bucket is left alone when it isn't.
+    fn rate_capped(&self) -> bool {
+        self.params.max_message_rate.value() > 0
+    }
+
+    /// Advance every smoothed channel by a block, queueing the values that are due to be sent.
+    fn advance_smoothers(&mut self, samples: u32) {
+        let emit_interval = self.sample_rate / self.params.smoothing_rate.value();
+        // The smoothers are swapped out so the values can be queued while iterating, without
+        // allocating
+        let mut smoothers = std::mem::take(&mut self.smoothers);
+        smoothers.advance(samples, emit_interval, |index, value, send| {
+            if send {
+                self.queue_update(index, value);
+            } else {
+                self.output_values[index] = value;
+            }
         });
+        self.smoothers = smoothers;
+    }
 
-        let sender = rx_sender.recv().unwrap();
-        self.sender = sender;
+    /// Move a channel to a new normalized value, either right away or through its smoother. This
+    /// always takes precedence over an ongoing fade.
+    fn set_channel(&mut self, index: usize, value: f32) {
+        self.home_fade.active[index] = false;
+
+        let smoothing_ms = match self.channel_settings[index].smoothing_ms {
+            smoothing_ms if smoothing_ms > 0.0 => smoothing_ms,
+            _ if self.interpolate => self.interpolation_ms(index),
+            _ => 0.0,
+        };
+        if smoothing_ms > 0.0 {
+            let current = self.output_values[index];
+            self.smoothers
+                .start(index, smoothing_ms, self.sample_rate, current, value);
+        } else {
+            self.smoothers.reset(index, value);
+            self.queue_update(index, value);
+        }
+    }
+
+    /// How long an interpolated channel ramps to its new value. This is the time since its previous
+    /// change so consecutive automation points are joined up, but at least one smoothing interval.
+    fn interpolation_ms(&mut self, index: usize) -> f32 {
+        let min_ms = 1000.0 / self.params.smoothing_rate.value();
+        let previous = self.last_change_sample[index].replace(self.sample_counter);
+        let elapsed_ms = previous
+            .map(|previous| (self.sample_counter - previous) as f32 / self.sample_rate * 1000.0);
+
+        match elapsed_ms {
+            // A channel that's been still for a while jumps after a single interval instead of
+            // crawling towards its first new value
+            Some(elapsed_ms) if elapsed_ms <= MAX_INTERPOLATION_MS => elapsed_ms.max(min_ms),
+            _ => min_ms,
+        }
+    }
+
+    /// Bind a CC to the channel that's armed for learning, if any, and set the channels bound to
+    /// it. CCs are handled just like parameter changes.
+    fn handle_cc(&mut self, context: &mut impl ProcessContext<Self>, cc: MidiCc, value: f32) {
+        // If the lock is taken, the channel stays armed until the next CC
+        if let Ok(mut learn_channel) = self.params.learn_channel.try_lock() {
+            if let Some(index) = learn_channel.take() {
+                // The channel config is updated in the background, this takes effect immediately
+                self.channel_settings[index].midi_cc = Some(cc);
+                context.execute_background(BackgroundTask::LearnCc { index, cc });
+                self.heartbeat.task_queued();
+            }
+        }
+
+        for index in 0..NUM_CHANNELS {
+            if self.channel_settings[index].midi_cc == Some(cc) {
+                self.set_channel(index, value);
+            }
+        }
+    }
+
+    /// Set every channel to the snapshot stored for a program, if there is one.
+    fn recall_snapshot(&mut self, program: u8) {
+        // The snapshots are borrowed through a separate reference so the channels can be set while
+        // they're locked
+        let params = Arc::clone(&self.params);
+        let Ok(snapshots) = params.snapshots.try_read() else {
+            nih_warn!("Could not recall the snapshot for program {program} since it's locked");
+            return;
+        };
+        let Some(snapshot) = snapshots
+            .iter()
+            .find(|snapshot| snapshot.program == program)
+        else {
+            return;
+        };
+
+        for (index, &value) in snapshot.values.iter().enumerate().take(NUM_CHANNELS) {
+            self.set_channel(index, value);
+        }
+    }
+
+    /// Send every channel's current value again, so receivers that missed something converge on
+    /// the right state. The channels are sent along with the block's other changes, and channels
+    /// that can't send right now do so as soon as they're allowed to.
+    fn resync(&mut self, context: &mut impl ProcessContext<Self>) {
+        self.pending[..self.channel_count].fill(true);
+
+        for param in BankParam::all() {
+            if self.holding {
+                self.dirty_banks.mark(param.flag());
+            } else {
+                self.send_bank_value(context, param);
+            }
+        }
+    }
+
+    fn send_bank_value(&mut self, context: &mut impl ProcessContext<Self>, param: BankParam) {
+        context.execute_background(BackgroundTask::Bank(param.value(&self.params)));
+        self.heartbeat.task_queued();
+        self.sent_this_block = true;
+    }
+
+    /// Whether the channel is currently allowed to send. Muted channels wait to be unmuted, gated
+    /// channels wait for the input gate to open, and channels with a send interval or a rate cap
+    /// wait for it to pass. The interval is ignored when the host doesn't report a tempo. Nothing
+    /// gets sent while the global rate cap is exhausted, or while holding for the transport to
+    /// start.
+    fn can_send(&self, index: usize) -> bool {
+        if self.holding || index >= self.channel_count {
+            return false;
+        }
+        if self.rate_capped() && self.rate_tokens < 1.0 {
+            return false;
+        }
+
+        let settings = &self.channel_settings[index];
+        if settings.muted || (settings.gated && !self.gate.is_open()) {
+            return false;
+        }
+
+        if settings.max_rate > 0.0 {
+            if let Some(last_sent_sample) = self.last_sent_sample[index] {
+                let min_samples = self.sample_rate as f64 / settings.max_rate as f64;
+                if ((self.sample_counter - last_sent_sample) as f64) < min_samples {
+                    return false;
+                }
+            }
+        }
+
+        match (
+            settings.send_interval.beats(),
+            self.tempo,
+            self.last_sent_sample[index],
+        ) {
+            (Some(beats), Some(tempo), Some(last_sent_sample)) => {
+                let interval_samples = beats * 60.0 / tempo * self.sample_rate as f64;
+                (self.sample_counter - last_sent_sample) as f64 >= interval_samples
+            }
+            _ => true,
+        }
+    }
+
+    /// Bind the OSC socket if there isn't one yet. This happens in `initialize()` rather than when
+    /// the plugin is created, since hosts create instances just to scan them. Errors are shown in
+    /// the editor, and if binding fails the task executor tries again and falls back to any port.
+    fn setup_sender(&self) {
+        // The watchdog holds on to the same `Arc`, so the socket needs to be swapped out in place
+        let mut sender = self.sender.lock().unwrap_or_else(PoisonError::into_inner);
+        if sender.is_some() {
+            return;
+        }
+
+        let local_bind = self.params.local_bind.read().unwrap();
+        *sender = match OscSocket::bind(&local_bind) {
+            Ok(new_sender) => {
+                self.status.set_bind_error(None);
+                Some(new_sender)
+            }
+            Err(err) => {
+                nih_error!("Could not bind the OSC socket to {local_bind}: {err}");
+                self.status.set_bind_error(Some(format!(
+                    "Could not bind the OSC socket to {local_bind}: {err}"
+                )));
+                None
+            }
+        };
     }
 }
 
 impl Default for SpaceRadio {
     fn default() -> Self {
-        let (tx_dirty_params, rx_dirty_params) = mpsc::channel();
-        thread::spawn(move || {
-            tx_dirty_params
-                .send(Arc::new(DashSet::<usize>::new()))
-                .unwrap();
-        });
-        let dirty_params = rx_dirty_params.recv().unwrap();
+        let dirty_params = Arc::new(DirtyFlags::new(NUM_CHANNELS));
+        let change_log = Arc::new(ChangeLog::new(NUM_CHANNELS));
+        let dirty_banks = Arc::new(DirtyFlags::new(NUM_BANK_PARAMS));
+        let home_requests = Arc::new(DirtyFlags::new(NUM_CHANNELS));
+        let send_all_requested = Arc::new(AtomicBool::new(false));
+        let params = Arc::new(SpaceRadioParams::new(
+            &dirty_params,
+            &change_log,
+            &dirty_banks,
+            &home_requests,
+            &send_all_requested,
+        ));
+        let sender = Arc::new(Mutex::new(None));
+        let heartbeat = Arc::new(Heartbeat::default());
+        let watchdog = Watchdog::spawn(
+            Arc::clone(&params),
+            Arc::clone(&heartbeat),
+            Arc::clone(&sender),
+        );
+        let addresses = Arc::new(ResolvedAddresses::default());
+        let resolver = Resolver::spawn(Arc::clone(&params), Arc::clone(&addresses));
+        let oscquery = OscQueryServer::spawn(Arc::clone(&params));
+        let discovery = Discovery::spawn(Arc::clone(&params), Arc::clone(&sender));
+        let remote_values = Arc::new(RemoteValues::default());
+        let automation = Arc::new(AutomationTarget::default());
+        let sync = Arc::new(SyncState::default());
+        let activity = Arc::new(ChannelActivity::default());
+        let traffic = Arc::new(TrafficLog::default());
+        let status = Arc::new(ConnectionStatus::default());
+        let (midi_output_sender, midi_output) = mpsc::sync_channel(midi::OUTPUT_QUEUE_LENGTH);
+        let link = LinkSession::spawn(Arc::clone(&params));
+        let analysis = Arc::new(AnalysisResults::default());
+        let (analyzer, analysis_input) =
+            Analyzer::spawn(Arc::clone(&params), Arc::clone(&analysis));
+        let output_snapshot = Arc::new(OutputSnapshot::new(NUM_CHANNELS));
+        let tick_executor = Arc::new(Mutex::new(None));
+        let ticker = SendTicker::spawn(
+            Arc::clone(&params),
+            Arc::clone(&output_snapshot),
+            Arc::clone(&tick_executor),
+        );
+        let receiver = OscReceiver::spawn(Arc::new(ReceiverContext {
+            params: Arc::clone(&params),
+            remote_values: Arc::clone(&remote_values),
+            automation: Arc::clone(&automation),
+            sync: Arc::clone(&sync),
+            traffic: Arc::clone(&traffic),
+            status: Arc::clone(&status),
+            midi_output: midi_output_sender,
+            executor: Arc::clone(&tick_executor),
+            home_requests: Arc::clone(&home_requests),
+        }));
 
-        let mut space_radio = Self {
-            params: Arc::new(SpaceRadioParams::new(&dirty_params)),
-            sender: Arc::new(Mutex::new(None)),
+        Self {
+            params,
+            sender,
+            addresses,
             dirty_params,
-        };
-
-        space_radio.setup_sender();
-        space_radio
+            dirty_banks,
+            home_requests,
+            homes_reached: Arc::new(DirtyFlags::new(NUM_CHANNELS)),
+            remote_values,
+            automation,
+            sync,
+            activity,
+            traffic,
+            status,
+            send_all_requested,
+            output_snapshot,
+            tick_executor,
+            ticking: false,
+            home_fade: Fade::new(NUM_CHANNELS),
+            output_values: vec![DEFAULT_CHANNEL_VALUE; NUM_CHANNELS],
+            sample_rate: 44100.0,
+            sample_counter: 0,
+            tempo: None,
+            was_playing: false,
+            holding: false,
+            frame: 0,
+            sent_this_block: false,
+            block_clock: BlockClock::default(),
+            gate: InputGate::default(),
+            level_meter: LevelMeter::default(),
+            loudness_meter: LoudnessMeter::default(),
+            stereo_meter: StereoMeter::default(),
+            envelope: EnvelopeFollower::default(),
+            analysis_input,
+            analysis,
+            channel_settings: vec![ChannelSettings::default(); NUM_CHANNELS],
+            smoothers: ChannelSmoothers::new(NUM_CHANNELS),
+            interpolate: false,
+            last_change_sample: vec![None; NUM_CHANNELS],
+            pending: vec![false; NUM_CHANNELS],
+            last_sent_sample: vec![None; NUM_CHANNELS],
+            last_sent_values: vec![None; NUM_CHANNELS],
+            echo_cooldown_ms: 0,
+            channel_count: DEFAULT_CHANNEL_COUNT as usize,
+            rate_tokens: 0.0,
+            round_robin: 0,
+            scratch_indices: Vec::with_capacity(NUM_CHANNELS),
+            metronome: Metronome::default(),
+            clock: Clock::default(),
+            transport: TransportBroadcast::default(),
+            tempo_broadcast: TempoBroadcast::default(),
+            phase_stream: PhaseStream::default(),
+            link_clock: LinkClock::default(),
+            mpe: MpeTracker::default(),
+            resync_elapsed: 0,
+            keepalive_elapsed: 0,
+            keepalive_count: 0,
+            heartbeat,
+            _watchdog: watchdog,
+            _resolver: resolver,
+            _oscquery: oscquery,
+            discovery,
+            _receiver: receiver,
+            link,
+            _analyzer: analyzer,
+            _ticker: ticker,
+            midi_output,
+        }
     }
 }
 
 impl SpaceRadioParams {
-    fn new(dirty_params: &Arc<DashSet<usize>>) -> Self {
+    fn new(
+        dirty_params: &Arc<DirtyFlags>,
+        change_log: &Arc<ChangeLog>,
+        dirty_banks: &Arc<DirtyFlags>,
+        home_requests: &Arc<DirtyFlags>,
+        send_all_requested: &Arc<AtomicBool>,
+    ) -> Self {
+        let home_requests = Arc::clone(home_requests);
+        let send_all_requested = Arc::clone(send_all_requested);
+        let resend_on_unmute = Arc::clone(&send_all_requested);
+
         Self {
-            array_params: (0..64)
+            array_params: (0..NUM_CHANNELS)
                 .map(|index| {
                     let dirty_params = Arc::clone(dirty_params);
+                    let change_log = Arc::clone(change_log);
                     ArrayParams {
                         val: FloatParam::new(
                             format!("Ch. {index}", index = index + 1),
-                            0.0,
+                            DEFAULT_CHANNEL_VALUE,
                             FloatRange::Linear { min: 0.0, max: 1.0 },
                         )
-                        .with_callback(Arc::new(move |_| {
-                            dirty_params.as_ref().insert(index);
+                        .with_poly_modulation_id(index as u32)
+                        .with_callback(Arc::new(move |value| {
+                            change_log.record(index, value);
+                            dirty_params.mark(index);
                         })),
                     }
                 })
                 .collect::<Vec<ArrayParams>>(),
-            osc_destination_address: RwLock::new("127.0.0.1".into()),
-            osc_destination_port: RwLock::new(9009),
+            int_params: banks::int_params(dirty_banks),
+            toggle_params: banks::toggle_params(dirty_banks),
+            scene_params: banks::scene_params(dirty_banks),
+            xy_params: banks::xy_params(dirty_banks),
+            editor_state: editor::default_state(),
+            destinations: RwLock::new(vec![Destination::default()]),
+            osc_namespace: RwLock::new(String::new()),
+            retarget_debounce_ms: RwLock::new(500),
+            local_bind: RwLock::new(LocalBind::default()),
+            oscquery_enabled: RwLock::new(false),
+            oscquery_port: RwLock::new(0),
+            advertise_mdns: RwLock::new(true),
+            receive_port: RwLock::new(0),
+            receive_group: RwLock::new(None),
+            write_automation: RwLock::new(true),
+            echo_cooldown_ms: RwLock::new(100),
+            sync_peers: RwLock::new(Vec::new()),
+            receive_allowlist: RwLock::new(Vec::new()),
+            accept_config: RwLock::new(false),
+            accept_all: RwLock::new(false),
+            pass_through: RwLock::new(false),
+            learn_channel: Mutex::new(None),
+            address_generation: AtomicU64::new(0),
+            active: AtomicBool::new(false),
+            channel_count: RwLock::new(DEFAULT_CHANNEL_COUNT),
+            channels: RwLock::new(vec![ChannelConfig::default(); NUM_CHANNELS]),
+            stamp_frame_ids: RwLock::new(false),
+            bundle_frames: RwLock::new(true),
+            send_every_change: RwLock::new(false),
+            timetag_latency_ms: RwLock::new(0.0),
+            include_interval: RwLock::new(false),
+            watchdog_timeout_ms: RwLock::new(2000),
+            keepalive_interval_ms: RwLock::new(0),
+            resync_interval_ms: RwLock::new(0),
+            send_tick_ms: RwLock::new(0),
+            only_while_playing: RwLock::new(false),
+            snapshot_on_play: RwLock::new(true),
+            mpe: RwLock::new(false),
+            levels_enabled: RwLock::new(false),
+            levels_interval_ms: RwLock::new(33),
+            analysis_source: RwLock::new(AnalysisSource::Main),
+            envelope_interval_ms: RwLock::new(16),
+            loudness_enabled: RwLock::new(false),
+            stereo_enabled: RwLock::new(false),
+            stereo_interval_ms: RwLock::new(33),
+            waveform_enabled: RwLock::new(false),
+            waveform_points: RwLock::new(256),
+            waveform_interval_ms: RwLock::new(16),
+            spectrum_enabled: RwLock::new(false),
+            spectrum_bands: RwLock::new(16),
+            spectrum_interval_ms: RwLock::new(33),
+            pitch_enabled: RwLock::new(false),
+            pitch_interval_ms: RwLock::new(33),
+            onset_enabled: RwLock::new(false),
+            onset_sensitivity: RwLock::new(1.5),
+            features_enabled: RwLock::new(false),
+            features_interval_ms: RwLock::new(33),
+            transport_enabled: RwLock::new(false),
+            transport_interval_ms: RwLock::new(50),
+            tempo_enabled: RwLock::new(false),
+            tempo_interval_ms: RwLock::new(0),
+            phase_enabled: RwLock::new(false),
+            phase_length: RwLock::new(PhaseLength::Bar),
+            phase_interval_ms: RwLock::new(16),
+            link_enabled: RwLock::new(false),
+            link_quantum: RwLock::new(4.0),
+            link_interval_ms: RwLock::new(16),
+            link_align_resync: RwLock::new(false),
+            snapshots: RwLock::new(Vec::new()),
+            clock_enabled: RwLock::new(false),
+            offset_by_instance: RwLock::new(false),
+            instance_number: RwLock::new(1),
+            channel_stride: RwLock::new(DEFAULT_CHANNEL_COUNT),
+            names_in_addresses: RwLock::new(false),
+            interpolate: RwLock::new(false),
+            non_finite_policy: RwLock::new(NonFinitePolicy::LastGood),
+            profiles: RwLock::new(OutputProfile::built_in()),
+            active_profile: RwLock::new(OutputProfile::default().name),
+            state_version: StateVersion::default(),
+            legacy_osc_address: RwLock::new(None),
+            legacy_osc_port: RwLock::new(None),
+            legacy_protocol: RwLock::new(None),
+            legacy_tcp_framing: RwLock::new(None),
+
+            go_home: BoolParam::new("Go Home", false).with_callback(Arc::new(move |on| {
+                if on {
+                    for index in 0..NUM_CHANNELS {
+                        home_requests.mark(index);
+                    }
+                }
+            })),
+            send_all: BoolParam::new("Send All", false).with_callback(Arc::new(move |on| {
+                if on {
+                    send_all_requested.store(true, Ordering::Relaxed);
+                }
+            })),
+            output_muted: BoolParam::new("Mute Output", false).with_callback(Arc::new(
+                move |muted| {
+                    if !muted {
+                        resend_on_unmute.store(true, Ordering::Relaxed);
+                    }
+                },
+            )),
+            home_fade_time: FloatParam::new(
+                "Home Fade",
+                1000.0,
+                FloatRange::Skewed {
+                    min: 0.0,
+                    max: 10_000.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_unit(" ms")
+            .with_step_size(1.0),
+            smoothing_rate: FloatParam::new(
+                "Smoothing Rate",
+                60.0,
+                FloatRange::Linear {
+                    min: 10.0,
+                    max: 200.0,
+                },
+            )
+            .with_unit(" Hz")
+            .with_step_size(1.0),
+            max_message_rate: IntParam::new(
+                "Max Message Rate",
+                0,
+                IntRange::Linear { min: 0, max: 5000 },
+            )
+            .with_value_to_string(Arc::new(|rate| {
+                if rate == 0 {
+                    String::from("Unlimited")
+                } else {
+                    format!("{rate} msg/s")
+                }
+            })),
+
+            gate: GateParams::default(),
+            metronome: MetronomeParams::default(),
+            envelope: EnvelopeParams::default(),
+        }
+    }
+}
+
+impl SpaceRadioParams {
+    /// Has the task executor and the receiver rebuild the channels' addresses. This needs to be
+    /// called after changing the namespace, the profiles, or the channels' names, groups, or
+    /// addresses.
+    fn invalidate_addresses(&self) {
+        self.address_generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Changes whenever [`Self::invalidate_addresses()`] is called.
+    fn address_generation(&self) -> u64 {
+        self.address_generation.load(Ordering::Relaxed)
+    }
+
+    /// The number of active channels.
+    fn channel_count(&self) -> usize {
+        (*self.channel_count.read().unwrap() as usize).clamp(1, NUM_CHANNELS)
+    }
+
+    /// The index used in a channel's address, which may be offset based on the instance number.
+    fn address_index(&self, index: usize) -> usize {
+        if !*self.offset_by_instance.read().unwrap() {
+            return index;
+        }
+
+        let instance_number = *self.instance_number.read().unwrap();
+        let channel_stride = *self.channel_stride.read().unwrap();
+        index + (instance_number.max(1) - 1) as usize * channel_stride as usize
+    }
+
+    /// The channel's address, without the namespace. The other channels are needed to work out
+    /// the channel's position within its group.
+    fn channel_address(
+        &self,
+        index: usize,
+        channels: &[ChannelConfig],
+        profile: &OutputProfile,
+    ) -> String {
+        let default_channel = ChannelConfig::default();
+        let channel = channels.get(index).unwrap_or(&default_channel);
+        if let Some(address) = &channel.address {
+            return address.clone();
         }
+
+        let address_index = self.address_index(index).to_string();
+        let name = channel.address_name();
+        let address_key = match &name {
+            Some(name) if *self.names_in_addresses.read().unwrap() => name,
+            _ => &address_index,
+        };
+        let member = if profile.uses_member() {
+            group_member(index, channels).to_string()
+        } else {
+            String::new()
+        };
+        profile.address(
+            address_key,
+            name.as_deref().unwrap_or(&address_index),
+            channel.address_group().as_deref().unwrap_or_default(),
+            &member,
+        )
     }
+
+    /// The selected output profile, or the default raw OSC profile if it no longer exists.
+    fn active_profile(&self) -> OutputProfile {
+        let name = self.active_profile.read().unwrap();
+        self.profiles
+            .read()
+            .unwrap()
+            .iter()
+            .find(|profile| profile.name == *name)
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+/// Where a frame of channel updates comes from. The audio thread's tasks and the sender thread's
+/// can be handled in between each other, so each builds its own bundles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FrameSource {
+    Block,
+    Tick,
 }
 
 enum BackgroundTask {
-    UpdateParameter { index: usize, value: f32 },
+    UpdateParameter {
+        index: usize,
+        value: f32,
+        frame: u32,
+        source: FrameSource,
+    },
+    /// Sent after the last update of a frame. When bundling is enabled, this sends that frame's
+    /// updates as a single bundle, timetagged with the frame's start time.
+    EndFrame {
+        time: SystemTime,
+        source: FrameSource,
+    },
+    /// Sends a bank parameter's value, see the `banks` module.
+    Bank(BankValue),
+    /// Sends `/tick <beat in bar>`, preceded by `/bar <bar>` on the first beat of a bar.
+    Tick { beat: u32, bar: u32 },
+    /// Sends `/heartbeat <count>`. The count wraps around, gaps in it mean heartbeats got lost.
+    KeepAlive { count: u32 },
+    /// Forwards a MIDI event the plugin received, see the `midi` module for the messages.
+    Midi(MidiMessage),
+    /// Stores a CC that was bound to a channel through MIDI learn in the channel's config.
+    LearnCc { index: usize, cc: MidiCc },
+    /// Sends a `/clock/...` message, see the `clock` module.
+    Clock(ClockMessage),
+    /// Sends `/level/rms` and `/level/peak`, see the `levels` module.
+    Levels(Levels),
+    /// Sends `/envelope`, see the `envelope` module.
+    Envelope(ChannelValues),
+    /// Sends `/loudness/momentary` and `/loudness/short`, see the `loudness` module.
+    Loudness(Loudness),
+    /// Sends the `/stereo/...` messages, see the `stereo` module.
+    Stereo(StereoImage),
+    /// Sends the most recent `/waveform` chunk, see the `analysis` module.
+    Waveform,
+    /// Sends the most recent `/spectrum`, see the `analysis` module.
+    Spectrum,
+    /// Sends `/pitch/hz` and `/pitch/confidence`, see the `pitch` module.
+    Pitch(Pitch),
+    /// Sends `/onset <strength>`, see the `onset` module.
+    Onset { strength: f32 },
+    /// Sends the `/spectral/...` features, see the `analysis` module.
+    Features(SpectralFeatures),
+    /// Sends a `/transport/...` message, see the `transport` module.
+    Transport(TransportMessage),
+    /// Sends a `/link/...` message, see the `link` module.
+    Link(LinkMessage),
+    /// Forwards a message received on the receive port as is, see the `receiver` module.
+    Forward(osc::Message),
+    /// Moves the parameters of the channels that finished their home fade to their home
+    /// positions, so the host's parameters end up where the output is. This needs the editor's
+    /// context, without it the output stays at home until the parameter is moved.
+    HomeReached,
     // SetupSender,
 }
 
@@ -113,20 +1805,26 @@ impl Plugin for SpaceRadio {
 
     const VERSION: &'static str = env!("CARGO_PKG_VERSION");
 
-    const DEFAULT_INPUT_CHANNELS: u32 = 0;
-    const DEFAULT_OUTPUT_CHANNELS: u32 = 1;
+    // The input is only analyzed, audio passes through untouched
+    const DEFAULT_INPUT_CHANNELS: u32 = 2;
+    const DEFAULT_OUTPUT_CHANNELS: u32 = 2;
 
-    const DEFAULT_AUX_INPUTS: Option<AuxiliaryIOConfig> = None;
+    // The sidechain can be analyzed instead of the main input
+    const DEFAULT_AUX_INPUTS: Option<AuxiliaryIOConfig> = Some(AuxiliaryIOConfig {
+        num_busses: 1,
+        num_channels: 2,
+    });
     const DEFAULT_AUX_OUTPUTS: Option<AuxiliaryIOConfig> = None;
 
-    const MIDI_INPUT: MidiConfig = MidiConfig::Basic;
+    const MIDI_INPUT: MidiConfig = MidiConfig::MidiCCs;
+    const MIDI_OUTPUT: MidiConfig = MidiConfig::MidiCCs;
 
     // Setting this to `true` will tell the wrapper to split the buffer up into smaller blocks
-    // whenever there are inter-buffer parameter changes. This way no changes to the plugin are
-    // required to support sample accurate automation and the wrapper handles all of the boring
-    // stuff like making sure transport and other timing information stays consistent between the
-    // splits.
-    const SAMPLE_ACCURATE_AUTOMATION: bool = true;
+    // whenever there are inter-buffer parameter changes. Every split would send its own changes
+    // then, so the buffer is left whole and each channel only sends its last value per buffer. The
+    // values in between are still seen by the parameters' callbacks, which record them for when
+    // `send_every_change` is enabled.
+    const SAMPLE_ACCURATE_AUTOMATION: bool = false;
 
     // More advanced plugins can use this to run expensive background tasks. See the field's
     // documentation for more information. `()` means that the plugin does not have any background
@@ -134,29 +1832,370 @@ impl Plugin for SpaceRadio {
     type BackgroundTask = BackgroundTask;
 
     fn task_executor(&self) -> TaskExecutor<Self> {
+        let params = Arc::clone(&self.params);
         let sender = Arc::clone(&self.sender);
-        let port = *self.params.osc_destination_port.read().unwrap();
-        let osc_destination_address = self.params.osc_destination_address.read().unwrap().clone();
+        let addresses = Arc::clone(&self.addresses);
+        let sync = Arc::clone(&self.sync);
+        let activity = Arc::clone(&self.activity);
+        let traffic = Arc::clone(&self.traffic);
+        let status = Arc::clone(&self.status);
+        let analysis = Arc::clone(&self.analysis);
+        let heartbeat = Arc::clone(&self.heartbeat);
+        let send_all_requested = Arc::clone(&self.send_all_requested);
+        let automation = Arc::clone(&self.automation);
+        let homes_reached = Arc::clone(&self.homes_reached);
+        let destinations = Mutex::new(DestinationCache::new(
+            &self.params.destinations.read().unwrap(),
+        ));
+        let state = Mutex::new(SendState::new(NUM_CHANNELS));
+        let failed_bind: Mutex<Option<LocalBind>> = Mutex::new(None);
+        let default_channel = ChannelConfig::default();
 
-        Box::new(move |task| match task {
-            BackgroundTask::UpdateParameter { index, value } => {
-                let sender = sender.lock().unwrap();
-                let target_addr = format!("{osc_destination_address}:{port}");
+        let execute: Executor = Arc::new(move |task| {
+            // Tasks that were still queued when the plugin was deactivated are dropped instead of
+            // reopening the socket
+            if !params.active.load(Ordering::Relaxed) {
+                return;
+            }
 
-                match sender.as_ref() {
-                    None => {
-                        // println!("No sender");
+            // A panicking send shouldn't silence the plugin for good, the watchdog will replace the
+            // socket if needed
+            let mut sender = sender.lock().unwrap_or_else(PoisonError::into_inner);
+            let local_bind = params.local_bind.read().unwrap();
+            let mut failed_bind = failed_bind.lock().unwrap();
+            if sender
+                .as_ref()
+                .map_or(true, |sender| sender.local_bind() != &*local_bind)
+                && failed_bind.as_ref() != Some(&*local_bind)
+            {
+                // The old socket needs to be closed first in case it holds the same fixed port
+                *sender = None;
+                *sender = match OscSocket::bind(&local_bind) {
+                    Ok(new_sender) => {
+                        nih_log!("Sending OSC messages from {local_bind}");
+                        status.set_bind_error(None);
+                        *failed_bind = None;
+                        Some(new_sender)
                     }
-                    Some(sender) => {
-                        let addr = format!("/{index}").to_string();
-                        let value = vec![osc::Type::Float(value)];
-                        // println!("Sent {index} {value:?}");
-                        sender
-                            .send((addr, value), target_addr)
-                            .expect("Could not send message");
+                    Err(err) => {
+                        // Sending from any port beats not sending at all. The setting isn't
+                        // retried until it changes.
+                        nih_error!("Could not bind the OSC socket to {local_bind}: {err}");
+                        status.set_bind_error(Some(format!(
+                            "Could not bind the OSC socket to {local_bind}: {err}"
+                        )));
+                        *failed_bind = Some(local_bind.clone());
+                        OscSocket::bind(&LocalBind::default())
+                            .map_err(|err| nih_error!("Could not bind a new OSC socket: {err}"))
+                            .ok()
+                    }
+                };
+            }
+            drop(local_bind);
+            drop(failed_bind);
+
+            if let Some(sender) = sender.as_mut() {
+                let mut destinations = destinations.lock().unwrap();
+                let retargeted = destinations.update(
+                    &params.destinations.read().unwrap(),
+                    Duration::from_millis(*params.retarget_debounce_ms.read().unwrap() as u64),
+                );
+                if retargeted {
+                    // Whatever listens at the new destinations hasn't heard the current values yet
+                    send_all_requested.store(true, Ordering::Relaxed);
+                }
+                let destinations = destinations.current();
+                let sync_peers = params.sync_peers.read().unwrap();
+                let is_target = |target: &str| {
+                    destinations
+                        .iter()
+                        .chain(sync_peers.iter())
+                        .any(|destination| destination.enabled && destination.target() == target)
+                };
+                sender.retain_tcp_targets(is_target);
+                status.retain_targets(is_target);
+
+                let namespace = params.osc_namespace.read().unwrap();
+                let namespace = namespace.trim_end_matches('/');
+                let message = |addr: String, args: Vec<osc::Type>| {
+                    osc::Packet::Message(osc::Message {
+                        addr: format!("{namespace}{addr}"),
+                        args,
+                    })
+                };
+                let mut send_to = |packet: osc::Packet, destinations: &[Destination]| {
+                    if params.output_muted.value() {
+                        return;
+                    }
+
+                    traffic.record_packet(Direction::Sent, &packet);
+                    let bytes = match socket::encode(&packet) {
+                        Ok(bytes) => bytes,
+                        Err(err) => {
+                            nih_warn!("Could not encode {packet:?}: {err}");
+                            return;
+                        }
+                    };
+
+                    for destination in destinations.iter() {
+                        if !destination.enabled {
+                            continue;
+                        }
+
+                        // Hostnames that haven't been resolved yet are skipped, the resolver logs
+                        // why if they can't be resolved at all
+                        let target = destination.target();
+                        let Some(addr) = addresses.lookup(&target) else {
+                            let err = String::from("The address has not been resolved");
+                            status.send_result(&target, Err(err));
+                            continue;
+                        };
+                        // Destinations that keep failing are only retried every once in a while
+                        if !status.should_send(&target) {
+                            continue;
+                        }
+                        match sender.send(&bytes, destination, addr) {
+                            Ok(()) => {
+                                status.send_result(&target, Ok(()));
+                            }
+                            Err(err) => {
+                                let failures = status.send_result(&target, Err(err.to_string()));
+                                if failures == 1 || failures % 100 == 0 {
+                                    nih_warn!(
+                                        "Could not send message to {target}: {err}, {failures} \
+                                         times in a row"
+                                    );
+                                }
+                            }
+                        }
+                    }
+                };
+
+                match task {
+                    BackgroundTask::UpdateParameter {
+                        index,
+                        value,
+                        frame,
+                        source,
+                    } => {
+                        // Sync peers get the normalized value since they apply their own
+                        // transforms, and no namespace since they may use a different one
+                        if !sync_peers.is_empty() {
+                            if let Some((addr, args)) = sync.local_change(index, value) {
+                                let packet = osc::Packet::Message(osc::Message { addr, args });
+                                send_to(packet, &sync_peers);
+                            }
+                        }
+
+                        let channels = params.channels.read().unwrap();
+                        let channel = channels.get(index).unwrap_or(&default_channel);
+                        let mut state = state.lock().unwrap();
+                        state.refresh(&params);
+                        let profile = Arc::clone(&state.profile);
+                        let value = state
+                            .sanitize(
+                                index,
+                                channel.transform(value, &profile),
+                                *params.non_finite_policy.read().unwrap(),
+                                channel.output_range(&profile),
+                            )
+                            .map(|value| channel.encode(value, &profile));
+                        // The address is only copied for values that actually get sent
+                        let value = value.map(|value| {
+                            let addr = state.address(index, || {
+                                let address = params.channel_address(index, &channels, &profile);
+                                format!("{namespace}{address}")
+                            });
+                            (addr.to_owned(), value)
+                        });
+                        let routes = channel.routes.clone();
+                        drop(channels);
+
+                        if let Some((addr, value)) = value {
+                            activity.record(index, value.clone());
+                            let mut args = vec![value];
+                            let interval = state.interval(index);
+                            if *params.include_interval.read().unwrap() {
+                                args.push(osc::Type::Float(interval));
+                            }
+                            if *params.stamp_frame_ids.read().unwrap() {
+                                args.push(osc::Type::Int(frame as i32));
+                            }
+
+                            let bundle_frames = *params.bundle_frames.read().unwrap()
+                                || *params.send_every_change.read().unwrap();
+                            let mut dispatch =
+                                |state: &mut SendState, packet: osc::Packet, target: &str| {
+                                    if bundle_frames {
+                                        state.bundle(source, target, packet);
+                                    } else {
+                                        send_to(packet, routing::select(destinations, target));
+                                    }
+                                };
+                            if routes.is_empty() {
+                                // The address already includes the namespace
+                                let packet = osc::Packet::Message(osc::Message { addr, args });
+                                dispatch(&mut state, packet, "");
+                            }
+                            for route in &routes {
+                                let addr = if route.address.is_empty() {
+                                    addr.clone()
+                                } else {
+                                    format!("{namespace}{}", route.address)
+                                };
+                                let args = args.clone();
+                                let packet = osc::Packet::Message(osc::Message { addr, args });
+                                dispatch(&mut state, packet, &route.destination);
+                            }
+                        }
+                    }
+                    BackgroundTask::EndFrame { time, source } => {
+                        // Receivers that honor timetags can then schedule the changes for when
+                        // the block is actually heard
+                        let latency = *params.timetag_latency_ms.read().unwrap();
+                        let time = time + Duration::from_secs_f32(latency.max(0.0) / 1000.0);
+                        let bundles = state.lock().unwrap().take_bundles(source);
+                        for (target, content) in bundles {
+                            let bundle = osc::Packet::Bundle(osc::Bundle {
+                                timetag: timetag::from_system_time(time),
+                                content,
+                            });
+                            send_to(bundle, routing::select(destinations, &target));
+                        }
+                    }
+                    BackgroundTask::Bank(value) => {
+                        let (addr, args) = value.to_osc();
+                        send_to(message(addr, args), destinations);
+                    }
+                    BackgroundTask::Tick { beat, bar } => {
+                        if beat == 0 {
+                            let bar = osc::Type::Int(bar as i32);
+                            send_to(message(String::from("/bar"), vec![bar]), destinations);
+                        }
+                        let beat = osc::Type::Int(beat as i32);
+                        send_to(message(String::from("/tick"), vec![beat]), destinations);
+                    }
+                    BackgroundTask::KeepAlive { count } => {
+                        let count = osc::Type::Int(count as i32);
+                        send_to(
+                            message(String::from("/heartbeat"), vec![count]),
+                            destinations,
+                        );
+                    }
+                    BackgroundTask::Midi(midi) => {
+                        let (addr, args) = midi.to_osc();
+                        send_to(message(addr, args), destinations);
+                    }
+                    BackgroundTask::Forward(forwarded) => {
+                        // Forwarding to this instance's own receive port would send the message
+                        // around in circles
+                        let receive_port = *params.receive_port.read().unwrap();
+                        let destinations: Vec<Destination> = destinations
+                            .iter()
+                            .filter(|destination| {
+                                destination.port != receive_port
+                                    || !addresses
+                                        .lookup(&destination.target())
+                                        .is_some_and(|addr| addr.ip().is_loopback())
+                            })
+                            .cloned()
+                            .collect();
+                        send_to(osc::Packet::Message(forwarded), &destinations);
+                    }
+                    BackgroundTask::Clock(clock) => {
+                        send_to(
+                            message(String::from(clock.address()), Vec::new()),
+                            destinations,
+                        );
+                    }
+                    BackgroundTask::Levels(levels) => {
+                        let rms = levels.rms.to_args();
+                        send_to(message(String::from("/level/rms"), rms), destinations);
+                        let peak = levels.peak.to_args();
+                        send_to(message(String::from("/level/peak"), peak), destinations);
+                    }
+                    BackgroundTask::Envelope(envelopes) => {
+                        let args = envelopes.to_args();
+                        send_to(message(String::from("/envelope"), args), destinations);
+                    }
+                    BackgroundTask::Loudness(loudness) => {
+                        for (addr, args) in loudness.to_osc() {
+                            send_to(message(String::from(addr), args), destinations);
+                        }
+                    }
+                    BackgroundTask::Stereo(image) => {
+                        for (addr, args) in image.to_osc() {
+                            send_to(message(String::from(addr), args), destinations);
+                        }
+                    }
+                    BackgroundTask::Waveform => {
+                        let args = vec![osc::Type::Blob(analysis.waveform_blob())];
+                        send_to(message(String::from("/waveform"), args), destinations);
+                    }
+                    BackgroundTask::Spectrum => {
+                        let bands = analysis.spectrum_args();
+                        send_to(message(String::from("/spectrum"), bands), destinations);
+                    }
+                    BackgroundTask::Pitch(pitch) => {
+                        for (addr, args) in pitch.to_osc() {
+                            send_to(message(String::from(addr), args), destinations);
+                        }
+                    }
+                    BackgroundTask::Onset { strength } => {
+                        let args = vec![osc::Type::Float(strength)];
+                        send_to(message(String::from("/onset"), args), destinations);
+                    }
+                    BackgroundTask::Features(features) => {
+                        for (addr, args) in features.to_osc() {
+                            send_to(message(String::from(addr), args), destinations);
+                        }
+                    }
+                    BackgroundTask::Transport(transport) => {
+                        let (addr, args) = transport.to_osc();
+                        send_to(message(addr, args), destinations);
+                    }
+                    BackgroundTask::Link(link) => {
+                        let (addr, args) = link.to_osc();
+                        send_to(message(addr, args), destinations);
+                    }
+                    BackgroundTask::LearnCc { index, cc } => {
+                        if let Some(channel) = params.channels.write().unwrap().get_mut(index) {
+                            nih_log!("Channel {} learned {cc}", index + 1);
+                            channel.midi_cc = Some(cc);
+                        }
+                    }
+                    BackgroundTask::HomeReached => {
+                        let context = automation.context();
+                        let channels = params.channels.read().unwrap();
+                        homes_reached.drain(|index| {
+                            let Some(context) = &context else {
+                                return;
+                            };
+                            let home = channels
+                                .get(index)
+                                .map_or(DEFAULT_CHANNEL_VALUE, |channel| channel.home);
+                            let setter = ParamSetter::new(&**context);
+                            let param = &params.array_params[index].val;
+                            setter.begin_set_parameter(param);
+                            setter.set_parameter_normalized(param, home);
+                            setter.end_set_parameter(param);
+                        });
                     }
                 }
             }
+        });
+        // A panicking task would otherwise take down the thread that runs all of them
+        let execute: Executor = Arc::new(move |task| {
+            if panic::catch_unwind(AssertUnwindSafe(|| execute(task))).is_err() {
+                nih_error!("A background task panicked");
+            }
+        });
+        // The sender thread runs the same tasks when ticking is enabled
+        *self.tick_executor.lock().unwrap() = Some(Arc::clone(&execute));
+
+        Box::new(move |task| {
+            execute(task);
+            heartbeat.task_handled();
         })
     }
 
@@ -164,46 +2203,507 @@ impl Plugin for SpaceRadio {
         self.params.clone()
     }
 
+    fn editor(&self, _async_executor: AsyncExecutor<Self>) -> Option<Box<dyn Editor>> {
+        editor::create(
+            Arc::clone(&self.params),
+            Arc::clone(&self.automation),
+            Arc::clone(&self.activity),
+            Arc::clone(&self.traffic),
+            Arc::clone(&self.status),
+        )
+    }
+
     // fn accepts_bus_config(&self, config: &BusConfig) -> bool {
     //     // This works with any symmetrical IO layout
     //     config.num_input_channels == config.num_output_channels && config.num_input_channels > 0
     // }
 
-    // This plugin doesn't need any special initialization, but if you need to do anything expensive
-    // then this would be the place. State is kept around when the host reconfigures the
-    // plugin. If we do need special initialization, we could implement the `initialize()` and/or
-    // `reset()` methods
+    // Fades are timed in samples, so we need to know the sample rate. The parameters have been
+    // restored by now, so this is also where we find out what the channels were last set to.
     fn initialize(
         &mut self,
         _bus_config: &BusConfig,
-        _buffer_config: &BufferConfig,
+        buffer_config: &BufferConfig,
         _context: &mut impl InitContext<Self>,
     ) -> bool {
+        self.sample_rate = buffer_config.sample_rate;
+        self.analysis.set_sample_rate(self.sample_rate);
+        migration::migrate(&self.params);
+        // This is also called after restoring state, which may have changed every address
+        self.params.invalidate_addresses();
+        self.params.active.store(true, Ordering::Relaxed);
+        self.setup_sender();
+        for (output_value, array_param) in
+            self.output_values.iter_mut().zip(&self.params.array_params)
+        {
+            *output_value = array_param.val.value();
+        }
+
         true
     }
 
+    fn reset(&mut self) {
+        self.block_clock.reset();
+        self.gate.reset();
+        self.level_meter.reset();
+        self.loudness_meter.reset();
+        self.stereo_meter.reset();
+        self.envelope.reset();
+        self.metronome.reset();
+        self.clock.reset();
+        self.transport.reset();
+        self.tempo_broadcast.reset();
+        self.phase_stream.reset();
+        self.link_clock.reset();
+        self.mpe.reset();
+    }
+
     fn process(
         &mut self,
-        _buffer: &mut Buffer,
-        _aux: &mut AuxiliaryBuffers,
+        buffer: &mut Buffer,
+        aux: &mut AuxiliaryBuffers,
         context: &mut impl ProcessContext<Self>,
     ) -> ProcessStatus {
-        for index in self.dirty_params.iter() {
-            let value = self.params.array_params[*index].val.value();
-            context.execute_background(BackgroundTask::UpdateParameter {
-                index: *index,
-                value,
+        // The channel config may be locked by the host thread while it restores state, in which
+        // case we'll use last block's settings and pick up the home requests during the next block
+        if let Ok(channels) = self.params.channels.try_read() {
+            for (settings, channel) in self.channel_settings.iter_mut().zip(channels.iter()) {
+                *settings = ChannelSettings::from(channel);
+            }
+
+            if !self.home_requests.is_empty() {
+                let fade = &mut self.home_fade;
+                let smoothers = &mut self.smoothers;
+                let output_values = &self.output_values;
+                self.home_requests.drain(|index| {
+                    smoothers.reset(index, output_values[index]);
+                    fade.from[index] = output_values[index];
+                    fade.to[index] = channels
+                        .get(index)
+                        .map_or(DEFAULT_CHANNEL_VALUE, |channel| channel.home);
+                    fade.active[index] = true;
+                });
+                fade.position = 0;
+                fade.length =
+                    (self.params.home_fade_time.value() / 1000.0 * self.sample_rate) as u32;
+            }
+        }
+
+        self.sent_this_block = false;
+        if let Ok(send_tick_ms) = self.params.send_tick_ms.try_read() {
+            self.ticking = *send_tick_ms > 0;
+        }
+        if let Ok(echo_cooldown_ms) = self.params.echo_cooldown_ms.try_read() {
+            self.echo_cooldown_ms = *echo_cooldown_ms;
+        }
+        if let Ok(channel_count) = self.params.channel_count.try_read() {
+            self.channel_count = (*channel_count as usize).clamp(1, NUM_CHANNELS);
+        }
+        if let Ok(interpolate) = self.params.interpolate.try_read() {
+            self.interpolate = *interpolate;
+        }
+        if let Ok(send_every_change) = self.params.send_every_change.try_read() {
+            self.change_log.set_recording(*send_every_change);
+        }
+        self.tempo = context.transport().tempo;
+        let playing = context.transport().playing;
+        let transport_started = playing && !self.was_playing;
+        self.was_playing = playing;
+        if let Ok(only_while_playing) = self.params.only_while_playing.try_read() {
+            self.holding = *only_while_playing && !playing;
+        }
+
+        // The bucket holds up to a tenth of a second's worth of messages
+        let max_message_rate = self.params.max_message_rate.value() as f64;
+        let block_seconds = buffer.samples() as f64 / self.sample_rate as f64;
+        self.rate_tokens = (self.rate_tokens + max_message_rate * block_seconds)
+            .clamp(0.0, (max_message_rate * 0.1).max(1.0));
+
+        let analysis_source = self
+            .params
+            .analysis_source
+            .try_read()
+            .map_or(AnalysisSource::Main, |source| *source);
+        let input = analysis_source.select(buffer, aux);
+
+        let levels_enabled = self.params.levels_enabled.try_read().map(|on| *on);
+        let levels_interval_ms = self.params.levels_interval_ms.try_read().map(|ms| *ms);
+        match (levels_enabled, levels_interval_ms) {
+            (Ok(true), Ok(interval_ms)) => {
+                let levels = self
+                    .level_meter
+                    .process(input, interval_ms, self.sample_rate);
+                if let Some(levels) = levels {
+                    context.execute_background(BackgroundTask::Levels(levels));
+                    self.heartbeat.task_queued();
+                }
+            }
+            (Ok(false), _) => self.level_meter.reset(),
+            _ => (),
+        }
+
+        if self.params.envelope.enabled.value() {
+            if let Ok(interval_ms) = self.params.envelope_interval_ms.try_read().map(|ms| *ms) {
+                let envelopes = self.envelope.process(
+                    input,
+                    &self.params.envelope,
+                    interval_ms,
+                    self.sample_rate,
+                );
+                if let Some(envelopes) = envelopes {
+                    context.execute_background(BackgroundTask::Envelope(envelopes));
+                    self.heartbeat.task_queued();
+                }
+            }
+        } else {
+            self.envelope.reset();
+        }
+
+        match self.params.loudness_enabled.try_read().map(|on| *on) {
+            Ok(true) => {
+                if let Some(loudness) = self.loudness_meter.process(input, self.sample_rate) {
+                    context.execute_background(BackgroundTask::Loudness(loudness));
+                    self.heartbeat.task_queued();
+                }
+            }
+            Ok(false) => self.loudness_meter.reset(),
+            Err(_) => (),
+        }
+
+        let stereo_enabled = self.params.stereo_enabled.try_read().map(|on| *on);
+        let stereo_interval_ms = self.params.stereo_interval_ms.try_read().map(|ms| *ms);
+        match (stereo_enabled, stereo_interval_ms) {
+            (Ok(true), Ok(interval_ms)) => {
+                let image = self
+                    .stereo_meter
+                    .process(input, interval_ms, self.sample_rate);
+                if let Some(image) = image {
+                    context.execute_background(BackgroundTask::Stereo(image));
+                    self.heartbeat.task_queued();
+                }
+            }
+            (Ok(false), _) => self.stereo_meter.reset(),
+            _ => (),
+        }
+
+        if analysis::wants_input(&self.params) {
+            self.analysis_input.push(input);
+        }
+        if self.analysis.take_spectrum_ready() {
+            context.execute_background(BackgroundTask::Spectrum);
+            self.heartbeat.task_queued();
+        }
+        if let Some(pitch) = self.analysis.take_pitch() {
+            context.execute_background(BackgroundTask::Pitch(pitch));
+            self.heartbeat.task_queued();
+        }
+        if let Some(strength) = self.analysis.take_onset() {
+            context.execute_background(BackgroundTask::Onset { strength });
+            self.heartbeat.task_queued();
+        }
+        if let Some(features) = self.analysis.take_features() {
+            context.execute_background(BackgroundTask::Features(features));
+            self.heartbeat.task_queued();
+        }
+        if self.analysis.take_waveform_ready() {
+            context.execute_background(BackgroundTask::Waveform);
+            self.heartbeat.task_queued();
+        }
+
+        self.gate
+            .process(input, &self.params.gate, self.sample_rate);
+
+        // Every run starts from a consistent state before any changes are streamed
+        if transport_started
+            && self
+                .params
+                .snapshot_on_play
+                .try_read()
+                .map_or(false, |on| *on)
+        {
+            self.resync(context);
+        }
+
+        // This swaps the scratch buffer out so we can call `&mut self` methods while iterating,
+        // without allocating
+        let mut updates = std::mem::take(&mut self.scratch_indices);
+        updates.clear();
+        self.dirty_params.drain(|index| updates.push(index));
+
+        for &index in &updates {
+            let value = self.params.array_params[index].val.value();
+            self.set_channel(index, value);
+        }
+        self.send_superseded_changes(context);
+
+        // The banks aren't smoothed or rate limited, but they do wait for the transport
+        if !self.holding {
+            let dirty_banks = Arc::clone(&self.dirty_banks);
+            dirty_banks.drain(|flag| self.send_bank_value(context, BankParam::from_flag(flag)));
+        }
+
+        // Values received over OSC are handled just like parameter changes
+        updates.clear();
+        self.remote_values.drain(&mut updates);
+        for &index in &updates {
+            let value = self.remote_values.value(index);
+            self.set_channel(index, value);
+        }
+        self.scratch_indices = updates;
+
+        // Incoming MIDI is forwarded over OSC, and MIDI received over OSC goes out the other way
+        let mpe = self.params.mpe.try_read().map_or(false, |on| *on);
+        while let Some(event) = context.next_event() {
+            if let Some(voice) = MidiMessage::from_voice_event(event) {
+                context.execute_background(BackgroundTask::Midi(voice));
+                self.heartbeat.task_queued();
+            }
+            // There are no voices to keep alive for polyphonic modulation after a note ends
+            if let NoteEvent::NoteOff {
+                timing,
+                voice_id,
+                channel,
+                note,
+                ..
+            } = event
+            {
+                context.send_event(NoteEvent::VoiceTerminated {
+                    timing,
+                    voice_id,
+                    channel,
+                    note,
+                });
+            }
+
+            if let Some(midi) = MidiMessage::from_event(event) {
+                match midi {
+                    MidiMessage::Cc { channel, cc, value } => {
+                        self.handle_cc(context, MidiCc { channel, cc }, value)
+                    }
+                    MidiMessage::ProgramChange { program, .. } => self.recall_snapshot(program),
+                    _ => (),
+                }
+
+                context.execute_background(BackgroundTask::Midi(midi));
+                self.heartbeat.task_queued();
+
+                if let Some(per_note) = self.mpe.process(midi).filter(|_| mpe) {
+                    context.execute_background(BackgroundTask::Midi(per_note));
+                    self.heartbeat.task_queued();
+                }
+            }
+        }
+        while let Ok(midi) = self.midi_output.try_recv() {
+            if let Some(event) = midi.to_event(0) {
+                context.send_event(event);
+            }
+        }
+
+        self.advance_smoothers(buffer.samples() as u32);
+
+        if self.home_fade.is_running() {
+            let progress = self.home_fade.advance(buffer.samples() as u32);
+            for index in 0..NUM_CHANNELS {
+                if !self.home_fade.active[index] {
+                    continue;
+                }
+
+                let value = self.home_fade.value(index, progress);
+                self.queue_update(index, value);
+
+                if progress >= 1.0 {
+                    self.home_fade.active[index] = false;
+                    self.homes_reached.mark(index);
+                }
+            }
+
+            if progress >= 1.0 {
+                context.execute_background(BackgroundTask::HomeReached);
+                self.heartbeat.task_queued();
+            }
+        }
+
+        let link_quantum = self.params.link_quantum.try_read().map(|quantum| *quantum);
+        let link_interval_ms = self.params.link_interval_ms.try_read().map(|ms| *ms);
+        let link_align_resync = self.params.link_align_resync.try_read().map(|on| *on);
+        let mut link_quantum_started = false;
+        if let (Ok(quantum), Ok(interval_ms)) = (link_quantum, link_interval_ms) {
+            let heartbeat = &self.heartbeat;
+            link_quantum_started = self.link_clock.process(
+                self.link.link(),
+                quantum,
+                interval_ms,
+                buffer.samples(),
+                self.sample_rate,
+                |link| {
+                    context.execute_background(BackgroundTask::Link(link));
+                    heartbeat.task_queued();
+                },
+            );
+        }
+        let link_aligned = matches!(link_align_resync, Ok(true)) && self.link.link().is_enabled();
+
+        if self.send_all_requested.swap(false, Ordering::Relaxed) {
+            // This is the one thing that's sent while holding
+            let holding = std::mem::replace(&mut self.holding, false);
+            self.resync(context);
+            self.flush_pending(context);
+            self.holding = holding;
+            self.resync_elapsed = 0;
+        } else if link_aligned {
+            if link_quantum_started {
+                self.resync(context);
+            }
+        } else if let Ok(interval_ms) = self.params.resync_interval_ms.try_read().map(|ms| *ms) {
+            let interval_samples = (interval_ms as f32 / 1000.0 * self.sample_rate) as u64;
+            self.resync_elapsed += buffer.samples() as u64;
+            if interval_ms > 0 && self.resync_elapsed >= interval_samples {
+                self.resync(context);
+                self.resync_elapsed = 0;
+            }
+        }
+
+        // Every change made during this block, as well as channels that were held back earlier, go
+        // out here with their latest value
+        self.flush_pending(context);
+
+        if self.sent_this_block {
+            let time = self
+                .block_clock
+                .block_start(self.sample_counter, self.sample_rate);
+            context.execute_background(BackgroundTask::EndFrame {
+                time,
+                source: FrameSource::Block,
             });
+            self.heartbeat.task_queued();
+            self.frame = self.frame.wrapping_add(1);
         }
+        self.sample_counter += buffer.samples() as u64;
 
-        self.dirty_params.clear();
+        let beats_per_bar = self.params.metronome.beats_per_bar.value() as i64;
+        let host_position = Metronome::host_position(context.transport());
+        let heartbeat = &self.heartbeat;
+        self.metronome.process(
+            &self.params.metronome,
+            host_position,
+            buffer.samples(),
+            self.sample_rate,
+            |beat| {
+                context.execute_background(BackgroundTask::Tick {
+                    beat: beat.rem_euclid(beats_per_bar) as u32,
+                    bar: beat.div_euclid(beats_per_bar) as u32,
+                });
+                heartbeat.task_queued();
+            },
+        );
+
+        if self.params.clock_enabled.try_read().map_or(false, |on| *on) {
+            let transport = context.transport();
+            let (playing, pos_beats, tempo) = (playing, transport.pos_beats(), transport.tempo);
+            let heartbeat = &self.heartbeat;
+            self.clock.process(
+                playing,
+                pos_beats,
+                tempo,
+                buffer.samples(),
+                self.sample_rate,
+                |clock| {
+                    context.execute_background(BackgroundTask::Clock(clock));
+                    heartbeat.task_queued();
+                },
+            );
+        } else {
+            self.clock.reset();
+        }
+
+        let transport_enabled = self.params.transport_enabled.try_read().map(|on| *on);
+        let transport_interval_ms = self.params.transport_interval_ms.try_read().map(|ms| *ms);
+        match (transport_enabled, transport_interval_ms) {
+            (Ok(true), Ok(interval_ms)) => {
+                let info = TransportInfo::new(context.transport());
+                let heartbeat = &self.heartbeat;
+                self.transport.process(
+                    info,
+                    interval_ms,
+                    buffer.samples(),
+                    self.sample_rate,
+                    |transport| {
+                        context.execute_background(BackgroundTask::Transport(transport));
+                        heartbeat.task_queued();
+                    },
+                );
+            }
+            // Everything is sent again once this is switched back on
+            (Ok(false), _) => self.transport.reset(),
+            _ => (),
+        }
+
+        let tempo_enabled = self.params.tempo_enabled.try_read().map(|on| *on);
+        let tempo_interval_ms = self.params.tempo_interval_ms.try_read().map(|ms| *ms);
+        match (tempo_enabled, tempo_interval_ms) {
+            (Ok(true), Ok(interval_ms)) => {
+                let heartbeat = &self.heartbeat;
+                self.tempo_broadcast.process(
+                    self.tempo,
+                    interval_ms,
+                    buffer.samples(),
+                    self.sample_rate,
+                    |tempo| {
+                        context.execute_background(BackgroundTask::Transport(tempo));
+                        heartbeat.task_queued();
+                    },
+                );
+            }
+            (Ok(false), _) => self.tempo_broadcast.reset(),
+            _ => (),
+        }
+
+        let phase_enabled = self.params.phase_enabled.try_read().map(|on| *on);
+        let phase_length = self.params.phase_length.try_read().map(|length| *length);
+        let phase_interval_ms = self.params.phase_interval_ms.try_read().map(|ms| *ms);
+        match (phase_enabled, phase_length, phase_interval_ms) {
+            (Ok(true), Ok(length), Ok(interval_ms)) => {
+                let phase = length.phase(context.transport());
+                let heartbeat = &self.heartbeat;
+                self.phase_stream.process(
+                    phase,
+                    playing,
+                    interval_ms,
+                    buffer.samples(),
+                    self.sample_rate,
+                    |phase| {
+                        context.execute_background(BackgroundTask::Transport(phase));
+                        heartbeat.task_queued();
+                    },
+                );
+            }
+            (Ok(false), _, _) => self.phase_stream.reset(),
+            _ => (),
+        }
+
+        // This keeps going while nothing moves so receivers can tell the plugin is still alive
+        if let Ok(interval_ms) = self.params.keepalive_interval_ms.try_read().map(|ms| *ms) {
+            let interval_samples = (interval_ms as f32 / 1000.0 * self.sample_rate) as u64;
+            self.keepalive_elapsed += buffer.samples() as u64;
+            if interval_ms > 0 && self.keepalive_elapsed >= interval_samples {
+                context.execute_background(BackgroundTask::KeepAlive {
+                    count: self.keepalive_count,
+                });
+                self.heartbeat.task_queued();
+                self.keepalive_elapsed = 0;
+                self.keepalive_count = self.keepalive_count.wrapping_add(1);
+            }
+        }
 
         ProcessStatus::Normal
     }
 
-    // This can be used for cleaning up special resources like socket connections whenever the
-    // plugin is deactivated. Most plugins won't need to do anything here.
-    fn deactivate(&mut self) {}
+    // The sockets are closed here so their ports are free for other instances, and they're opened
+    // again in `initialize()`. The receiver closes its own socket once it notices.
+    fn deactivate(&mut self) {
+        self.params.active.store(false, Ordering::Relaxed);
+        *self.sender.lock().unwrap_or_else(PoisonError::into_inner) = None;
+    }
 }
 
 impl ClapPlugin for SpaceRadio {
@@ -212,6 +2712,12 @@ impl ClapPlugin for SpaceRadio {
     const CLAP_MANUAL_URL: Option<&'static str> = Some(Self::URL);
     const CLAP_SUPPORT_URL: Option<&'static str> = None;
     const CLAP_FEATURES: &'static [ClapFeature] = &[ClapFeature::Utility, ClapFeature::Instrument];
+
+    // The channels' polyphonic modulation is forwarded per voice, see the `midi` module
+    const CLAP_POLY_MODULATION_CONFIG: Option<PolyModulationConfig> = Some(PolyModulationConfig {
+        max_voice_capacity: midi::MAX_VOICES,
+        supports_overlapping_voices: true,
+    });
 }
 
 impl Vst3Plugin for SpaceRadio {
@@ -221,3 +2727,101 @@ impl Vst3Plugin for SpaceRadio {
 
 nih_export_clap!(SpaceRadio);
 nih_export_vst3!(SpaceRadio);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn silence_maps_to_the_floor() {
+        let range = DecibelRange::default();
+        assert_eq!(range.gain_to_db(0.0), range.silence);
+        assert_eq!(range.gain_to_db(-1.0), range.silence);
+    }
+
+    #[test]
+    fn quiet_gains_stop_at_the_bottom_of_the_range() {
+        let range = DecibelRange::default();
+        assert!((range.gain_to_db(util::db_to_gain(range.min)) - range.min).abs() < 1e-3);
+        assert_eq!(range.gain_to_db(0.0001), range.min);
+        assert_eq!(range.gain_to_db(f32::MIN_POSITIVE), range.min);
+    }
+
+    #[test]
+    fn unity_gain_is_the_top_of_the_range() {
+        let range = DecibelRange::default();
+        assert_eq!(range.gain_to_db(1.0), 0.0);
+        assert_eq!(range.gain_to_db(2.0), range.max);
+
+        let range = DecibelRange {
+            max: 6.0,
+            ..DecibelRange::default()
+        };
+        assert_eq!(range.gain_to_db(1.0), 0.0);
+    }
+
+    #[test]
+    fn namespaces_get_a_single_leading_slash() {
+        assert_eq!(normalize_namespace(""), "");
+        assert_eq!(normalize_namespace("/"), "");
+        assert_eq!(normalize_namespace("radio"), "/radio");
+        assert_eq!(normalize_namespace("/radio/"), "/radio");
+        assert_eq!(normalize_namespace(" //space/radio// "), "/space/radio");
+    }
+
+    #[test]
+    fn normalize_inverts_transform() {
+        let curves = [
+            ResponseCurve::Linear,
+            ResponseCurve::Logarithmic,
+            ResponseCurve::Exponential,
+            ResponseCurve::Skew { factor: 0.5 },
+            ResponseCurve::Skew { factor: 2.0 },
+        ];
+        let ranges = [
+            None,
+            Some(ValueRange {
+                min: -10.0,
+                max: 10.0,
+            }),
+            Some(ValueRange { min: 1.0, max: 0.0 }),
+        ];
+        let modes = [
+            (None, None),
+            (Some(DecibelRange::default()), None),
+            (None, Some(ChannelValueType::Int { min: 0, max: 127 })),
+        ];
+        let profiles = OutputProfile::built_in();
+
+        for curve in curves {
+            for range in ranges {
+                for (decibels, value_type) in modes.clone() {
+                    for (scale, offset) in [(1.0, 0.0), (-2.0, 0.5)] {
+                        let config = ChannelConfig {
+                            curve,
+                            range,
+                            decibels: decibels.clone(),
+                            value_type: value_type.clone(),
+                            scale,
+                            offset,
+                            ..ChannelConfig::default()
+                        };
+
+                        for profile in &profiles {
+                            for value in [0.0, 0.1, 0.25, 0.5, 0.75, 1.0] {
+                                let transformed = config.transform(value, profile);
+                                let normalized = config.normalize(transformed, profile);
+                                assert!(
+                                    (normalized - value).abs() < 1e-3,
+                                    "{config:?} with {}: {value} became {transformed} and then \
+                                     {normalized}",
+                                    profile.name
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
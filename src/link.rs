@@ -0,0 +1,184 @@
+//! Joins an Ableton Link session and broadcasts its tempo and phase, so receivers that aren't part
+//! of the DAW's world at all can follow the same beat as everything else on the Link session.
+//!
+//! - `/link/tempo <bpm>` whenever the session's tempo changes.
+//! - `/link/beat <beat>` at the start of every beat.
+//! - `/link/phase <phase>` at the configured interval, ramping from 0 to 1 over every quantum.
+//!
+//! Periodic resyncs of all channels can also be aligned to the start of every quantum instead of
+//! following the resync interval, so full updates land on the session's downbeats.
+//!
+//! Joining and leaving the session happens on its own thread since it involves the network, the
+//! audio thread only reads the session's state.
+
+use nannou_osc as osc;
+use nih_plug::prelude::*;
+use rusty_link::{AblLink, SessionState};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::SpaceRadioParams;
+
+/// How often the thread checks whether Link has been switched on or off.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// The tempo the session starts with if there are no other peers.
+const DEFAULT_TEMPO: f64 = 120.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LinkMessage {
+    Tempo(f64),
+    Beat(i64),
+    Phase(f64),
+}
+
+impl LinkMessage {
+    /// The OSC address, without the namespace, and arguments for this message.
+    pub fn to_osc(self) -> (String, Vec<osc::Type>) {
+        match self {
+            LinkMessage::Tempo(tempo) => (
+                String::from("/link/tempo"),
+                vec![osc::Type::Float(tempo as f32)],
+            ),
+            LinkMessage::Beat(beat) => (String::from("/link/beat"), vec![osc::Type::Long(beat)]),
+            LinkMessage::Phase(phase) => (
+                String::from("/link/phase"),
+                vec![osc::Type::Float(phase as f32)],
+            ),
+        }
+    }
+}
+
+/// Enables and disables Link along with the `link_enabled` setting. Stops and joins its thread
+/// when dropped.
+pub struct LinkSession {
+    link: Arc<AblLink>,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl LinkSession {
+    pub(crate) fn spawn(params: Arc<SpaceRadioParams>) -> Self {
+        let link = Arc::new(AblLink::new(DEFAULT_TEMPO));
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = {
+            let link = Arc::clone(&link);
+            let stop = Arc::clone(&stop);
+            thread::Builder::new()
+                .name(String::from("space-radio-link"))
+                .spawn(move || run(&params, &link, &stop))
+                .ok()
+        };
+
+        Self { link, stop, handle }
+    }
+
+    pub fn link(&self) -> &AblLink {
+        &self.link
+    }
+}
+
+impl Drop for LinkSession {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+        self.link.enable(false);
+    }
+}
+
+fn run(params: &SpaceRadioParams, link: &AblLink, stop: &AtomicBool) {
+    let mut num_peers = 0;
+    while !stop.load(Ordering::Relaxed) {
+        let enabled = *params.link_enabled.read().unwrap();
+        if link.is_enabled() != enabled {
+            link.enable(enabled);
+            nih_log!(
+                "{} the Link session",
+                if enabled { "Joined" } else { "Left" }
+            );
+        }
+
+        let new_num_peers = link.num_peers();
+        if enabled && new_num_peers != num_peers {
+            nih_log!("The Link session now has {new_num_peers} other peers");
+        }
+        num_peers = new_num_peers;
+
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Reads the Link session's state on the audio thread.
+pub struct LinkClock {
+    /// Preallocated so capturing the state doesn't allocate.
+    state: SessionState,
+    last_tempo: Option<f64>,
+    last_beat: Option<i64>,
+    /// Samples since the phase was last sent.
+    elapsed: u64,
+}
+
+impl Default for LinkClock {
+    fn default() -> Self {
+        Self {
+            state: SessionState::new(),
+            last_tempo: None,
+            last_beat: None,
+            elapsed: 0,
+        }
+    }
+}
+
+impl LinkClock {
+    pub fn reset(&mut self) {
+        self.last_tempo = None;
+        self.last_beat = None;
+        self.elapsed = 0;
+    }
+
+    /// Call `send` with the messages for this block. The phase is sent every `interval_ms`, or
+    /// every block if that's zero. Returns whether a new quantum started during this block.
+    pub fn process(
+        &mut self,
+        link: &AblLink,
+        quantum: f64,
+        interval_ms: u32,
+        num_samples: usize,
+        sample_rate: f32,
+        mut send: impl FnMut(LinkMessage),
+    ) -> bool {
+        if !link.is_enabled() || quantum <= 0.0 {
+            self.reset();
+            return false;
+        }
+
+        link.capture_audio_session_state(&mut self.state);
+        let time = link.clock_micros();
+
+        let tempo = self.state.tempo();
+        if self.last_tempo != Some(tempo) {
+            send(LinkMessage::Tempo(tempo));
+            self.last_tempo = Some(tempo);
+        }
+
+        let beat = self.state.beat_at_time(time, quantum).floor() as i64;
+        let new_beat = self.last_beat.map_or(false, |last_beat| last_beat != beat);
+        if new_beat {
+            send(LinkMessage::Beat(beat));
+        }
+        self.last_beat = Some(beat);
+
+        let interval_samples = (interval_ms as f32 / 1000.0 * sample_rate) as u64;
+        if self.elapsed == 0 || self.elapsed >= interval_samples {
+            let phase = self.state.phase_at_time(time, quantum) / quantum;
+            send(LinkMessage::Phase(phase));
+            self.elapsed = 0;
+        }
+        self.elapsed += num_samples as u64;
+
+        new_beat && (beat as f64).rem_euclid(quantum) < 1.0
+    }
+}
@@ -0,0 +1,198 @@
+//! EBU R128 loudness of the plugin's audio input, for broadcast dashboards and show control that
+//! adapts to how loud the program is.
+//!
+//! - `/loudness/momentary <LUFS>` over the last 400 milliseconds.
+//! - `/loudness/short <LUFS>` over the last three seconds.
+//!
+//! Both are sent every 100 milliseconds. Every input channel is weighted equally, and silence
+//! bottoms out at [`MIN_LOUDNESS`].
+
+use nannou_osc as osc;
+use nih_plug::prelude::*;
+use std::f64::consts::PI;
+
+use crate::analysis::MAX_CHANNELS;
+
+/// The loudness reported for silence, this is also R128's absolute gate.
+pub const MIN_LOUDNESS: f32 = -70.0;
+/// The length of the blocks the measurement windows are made of.
+const BLOCK_MS: f64 = 100.0;
+const MOMENTARY_BLOCKS: usize = 4;
+const SHORT_TERM_BLOCKS: usize = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Loudness {
+    pub momentary: f32,
+    pub short_term: f32,
+}
+
+impl Loudness {
+    pub fn to_osc(self) -> [(&'static str, Vec<osc::Type>); 2] {
+        [
+            (
+                "/loudness/momentary",
+                vec![osc::Type::Float(self.momentary)],
+            ),
+            ("/loudness/short", vec![osc::Type::Float(self.short_term)]),
+        ]
+    }
+}
+
+#[derive(Default)]
+pub struct LoudnessMeter {
+    /// The sample rate the filters were set up for.
+    sample_rate: f32,
+    filters: [KWeighting; MAX_CHANNELS],
+    /// The summed mean square of every channel during the current block, not yet divided by the
+    /// block's length.
+    block_energy: f64,
+    block_samples: usize,
+    /// The mean square of the most recent blocks, as a ring buffer.
+    blocks: [f64; SHORT_TERM_BLOCKS],
+    next_block: usize,
+    num_blocks: usize,
+}
+
+impl LoudnessMeter {
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Measure the next block of input. Returns the loudness when a new 100 millisecond block has
+    /// been completed.
+    pub fn process(&mut self, buffer: &Buffer, sample_rate: f32) -> Option<Loudness> {
+        if self.sample_rate != sample_rate {
+            self.reset();
+            self.sample_rate = sample_rate;
+            self.filters = [KWeighting::new(sample_rate as f64); MAX_CHANNELS];
+        }
+
+        let block_length = (BLOCK_MS / 1000.0 * sample_rate as f64) as usize;
+        let channels = buffer.as_slice_immutable();
+        let mut loudness = None;
+        for sample_idx in 0..buffer.samples() {
+            for (filter, channel) in self.filters.iter_mut().zip(channels.iter()) {
+                let sample = filter.process(channel[sample_idx] as f64);
+                self.block_energy += sample * sample;
+            }
+
+            self.block_samples += 1;
+            if self.block_samples >= block_length {
+                self.blocks[self.next_block] = self.block_energy / self.block_samples as f64;
+                self.next_block = (self.next_block + 1) % SHORT_TERM_BLOCKS;
+                self.num_blocks = (self.num_blocks + 1).min(SHORT_TERM_BLOCKS);
+                self.block_energy = 0.0;
+                self.block_samples = 0;
+
+                loudness = Some(Loudness {
+                    momentary: self.window_loudness(MOMENTARY_BLOCKS),
+                    short_term: self.window_loudness(SHORT_TERM_BLOCKS),
+                });
+            }
+        }
+
+        loudness
+    }
+
+    /// The loudness over the last `num_blocks` blocks, or over all blocks if there aren't that many
+    /// yet.
+    fn window_loudness(&self, num_blocks: usize) -> f32 {
+        let num_blocks = num_blocks.min(self.num_blocks);
+        let energy = (1..=num_blocks)
+            .map(|age| self.blocks[(self.next_block + SHORT_TERM_BLOCKS - age) % SHORT_TERM_BLOCKS])
+            .sum::<f64>()
+            / num_blocks as f64;
+
+        ((-0.691 + 10.0 * energy.log10()) as f32).max(MIN_LOUDNESS)
+    }
+}
+
+/// R128's K-weighting filter, a high shelf modelling the head followed by a high pass.
+#[derive(Debug, Default, Clone, Copy)]
+struct KWeighting {
+    shelf: Biquad,
+    high_pass: Biquad,
+}
+
+impl KWeighting {
+    fn new(sample_rate: f64) -> Self {
+        Self {
+            shelf: Biquad::high_shelf(
+                sample_rate,
+                1681.974450955533,
+                0.7071752369554193,
+                3.99984385397,
+            ),
+            high_pass: Biquad::high_pass(sample_rate, 38.13547087613982, 0.5003270373253953),
+        }
+    }
+
+    fn process(&mut self, sample: f64) -> f64 {
+        self.high_pass.process(self.shelf.process(sample))
+    }
+}
+
+/// A transposed direct form II biquad.
+#[derive(Debug, Default, Clone, Copy)]
+struct Biquad {
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+    s1: f64,
+    s2: f64,
+}
+
+impl Biquad {
+    fn high_shelf(sample_rate: f64, frequency: f64, q: f64, gain_db: f64) -> Self {
+        let a = 10f64.powf(gain_db / 40.0);
+        let w0 = 2.0 * PI * frequency / sample_rate;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * q);
+        let sqrt_a_alpha = 2.0 * a.sqrt() * alpha;
+
+        Self::normalized(
+            a * ((a + 1.0) + (a - 1.0) * cos + sqrt_a_alpha),
+            -2.0 * a * ((a - 1.0) + (a + 1.0) * cos),
+            a * ((a + 1.0) + (a - 1.0) * cos - sqrt_a_alpha),
+            (a + 1.0) - (a - 1.0) * cos + sqrt_a_alpha,
+            2.0 * ((a - 1.0) - (a + 1.0) * cos),
+            (a + 1.0) - (a - 1.0) * cos - sqrt_a_alpha,
+        )
+    }
+
+    fn high_pass(sample_rate: f64, frequency: f64, q: f64) -> Self {
+        let w0 = 2.0 * PI * frequency / sample_rate;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * q);
+
+        Self::normalized(
+            (1.0 + cos) / 2.0,
+            -(1.0 + cos),
+            (1.0 + cos) / 2.0,
+            1.0 + alpha,
+            -2.0 * cos,
+            1.0 - alpha,
+        )
+    }
+
+    fn normalized(b0: f64, b1: f64, b2: f64, a0: f64, a1: f64, a2: f64) -> Self {
+        Self {
+            b0: b0 / a0,
+            b1: b1 / a0,
+            b2: b2 / a0,
+            a1: a1 / a0,
+            a2: a2 / a0,
+            s1: 0.0,
+            s2: 0.0,
+        }
+    }
+
+    fn process(&mut self, input: f64) -> f64 {
+        let output = self.b0 * input + self.s1;
+        self.s1 = self.b1 * input - self.a1 * output + self.s2;
+        self.s2 = self.b2 * input - self.a2 * output;
+        output
+    }
+}
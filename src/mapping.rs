@@ -0,0 +1,95 @@
+//! Exports the mapping from channels to OSC messages as a JSON file, and imports it again. This
+//! covers everything that decides what gets sent where, so a mapping can be versioned next to a
+//! show or shared with collaborators without sharing the whole project.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::destination::Destination;
+use crate::profile::OutputProfile;
+use crate::{normalize_namespace, ChannelConfig, SpaceRadioParams, NUM_CHANNELS};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Mapping {
+    destinations: Vec<Destination>,
+    namespace: String,
+    profiles: Vec<OutputProfile>,
+    active_profile: String,
+    names_in_addresses: bool,
+    channel_count: u32,
+    channels: Vec<ChannelConfig>,
+}
+
+impl Default for Mapping {
+    fn default() -> Self {
+        Self {
+            destinations: Vec::new(),
+            namespace: String::new(),
+            profiles: Vec::new(),
+            active_profile: String::new(),
+            names_in_addresses: false,
+            channel_count: NUM_CHANNELS as u32,
+            channels: Vec::new(),
+        }
+    }
+}
+
+impl Mapping {
+    pub(crate) fn from_params(params: &SpaceRadioParams) -> Self {
+        Self {
+            destinations: params.destinations.read().unwrap().clone(),
+            namespace: params.osc_namespace.read().unwrap().clone(),
+            profiles: params.profiles.read().unwrap().clone(),
+            active_profile: params.active_profile.read().unwrap().clone(),
+            names_in_addresses: *params.names_in_addresses.read().unwrap(),
+            channel_count: *params.channel_count.read().unwrap(),
+            channels: params.channels.read().unwrap().clone(),
+        }
+    }
+
+    /// Replace the current mapping with this one. Profiles that aren't part of the mapping are
+    /// kept, and channels missing from it get the default settings.
+    pub(crate) fn apply(mut self, params: &SpaceRadioParams) {
+        self.channels.resize(NUM_CHANNELS, ChannelConfig::default());
+
+        let mut profiles = params.profiles.write().unwrap();
+        for profile in self.profiles {
+            match profiles
+                .iter_mut()
+                .find(|stored| stored.name == profile.name)
+            {
+                Some(stored) => *stored = profile,
+                None => profiles.push(profile),
+            }
+        }
+        drop(profiles);
+
+        *params.destinations.write().unwrap() = self.destinations;
+        *params.osc_namespace.write().unwrap() = normalize_namespace(&self.namespace);
+        *params.active_profile.write().unwrap() = self.active_profile;
+        *params.names_in_addresses.write().unwrap() = self.names_in_addresses;
+        *params.channel_count.write().unwrap() = self.channel_count.clamp(1, NUM_CHANNELS as u32);
+        *params.channels.write().unwrap() = self.channels;
+        params.invalidate_addresses();
+    }
+}
+
+/// Write the current mapping to `path` as pretty-printed JSON.
+pub(crate) fn export(params: &SpaceRadioParams, path: &Path) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(&Mapping::from_params(params))
+        .map_err(|err| format!("Could not serialize the mapping: {err}"))?;
+    fs::write(path, json).map_err(|err| format!("Could not write {}: {err}", path.display()))
+}
+
+/// Read a mapping from `path` and apply it. Nothing changes if the file can't be read or parsed.
+pub(crate) fn import(params: &SpaceRadioParams, path: &Path) -> Result<(), String> {
+    let json = fs::read_to_string(path)
+        .map_err(|err| format!("Could not read {}: {err}", path.display()))?;
+    let mapping: Mapping = serde_json::from_str(&json)
+        .map_err(|err| format!("{} is not a valid mapping: {err}", path.display()))?;
+    mapping.apply(params);
+
+    Ok(())
+}
@@ -0,0 +1,103 @@
+//! A pulse source for tempo-driven visuals that keeps ticking when there's no host transport.
+
+use nih_plug::prelude::*;
+
+#[derive(Params)]
+pub struct MetronomeParams {
+    #[id = "metro_on"]
+    pub enabled: BoolParam,
+    #[id = "metro_bpm"]
+    pub bpm: FloatParam,
+    /// A `/bar` message is sent on the first tick of every bar.
+    #[id = "metro_bar"]
+    pub beats_per_bar: IntParam,
+    /// Tick along with the host's beat grid while its transport is playing.
+    #[id = "metro_host"]
+    pub follow_host: BoolParam,
+}
+
+impl Default for MetronomeParams {
+    fn default() -> Self {
+        Self {
+            enabled: BoolParam::new("Metronome", false),
+            bpm: FloatParam::new(
+                "Metronome BPM",
+                120.0,
+                FloatRange::Linear {
+                    min: 20.0,
+                    max: 300.0,
+                },
+            )
+            .with_step_size(0.1),
+            beats_per_bar: IntParam::new("Beats per Bar", 4, IntRange::Linear { min: 1, max: 16 }),
+            follow_host: BoolParam::new("Follow Host Transport", true),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Metronome {
+    /// The number of beats since the metronome was started, including the current partial beat.
+    position: f64,
+    /// The index of the next beat that should tick.
+    next_beat: i64,
+}
+
+impl Metronome {
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// The host's position in beats and its tempo, if its transport is playing and it reports both.
+    /// Needs to be queried before [`process()`][Self::process()] since that borrows the context.
+    pub fn host_position(transport: &Transport) -> Option<(f64, f64)> {
+        match (transport.playing, transport.tempo, transport.pos_beats()) {
+            (true, Some(tempo), Some(pos_beats)) => Some((pos_beats, tempo)),
+            _ => None,
+        }
+    }
+
+    /// Advance the metronome by one block and call `tick` with the index of every beat that starts
+    /// within it.
+    pub fn process(
+        &mut self,
+        params: &MetronomeParams,
+        host_position: Option<(f64, f64)>,
+        num_samples: usize,
+        sample_rate: f32,
+        mut tick: impl FnMut(i64),
+    ) {
+        if !params.enabled.value() {
+            self.reset();
+            return;
+        }
+
+        let block_seconds = num_samples as f64 / sample_rate as f64;
+        let (start, end) = match host_position.filter(|_| params.follow_host.value()) {
+            Some((pos_beats, tempo)) => {
+                // Jumping around in the timeline, or switching over from the internal clock,
+                // resynchronizes the ticks
+                let start = pos_beats;
+                if (start - self.position).abs() > 1e-3 {
+                    self.next_beat = start.ceil() as i64;
+                }
+                (start, start + block_seconds * tempo / 60.0)
+            }
+            None => {
+                let start = self.position;
+                (
+                    start,
+                    start + block_seconds * params.bpm.value() as f64 / 60.0,
+                )
+            }
+        };
+
+        while (self.next_beat as f64) < end {
+            if self.next_beat as f64 >= start {
+                tick(self.next_beat);
+            }
+            self.next_beat += 1;
+        }
+        self.position = end;
+    }
+}
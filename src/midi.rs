@@ -0,0 +1,571 @@
+//! Forwards the MIDI the plugin receives as OSC, so OSC-native visuals can react to the
+//! performance directly instead of only to the channels.
+//!
+//! - `/note/on <channel> <note> <velocity>`
+//! - `/note/off <channel> <note> <velocity>`
+//! - `/cc/<cc> <channel> <value>`
+//! - `/bend <channel> <value>`, where 0.5 means no bend.
+//! - `/pressure <channel> <pressure>` for channel aftertouch.
+//! - `/program <channel> <program>`, which also recalls the snapshot stored for that program.
+//!
+//! MIDI channels count from zero and all values are normalized to `[0, 1]`, like nih-plug reports
+//! them. These messages use the namespace. SysEx isn't forwarded, since the version of nih-plug
+//! this is built against has no `MidiConfig` that delivers SysEx to plugins.
+//!
+//! With MPE enabled, every note also gets an ID that's unique until it wraps around, and the
+//! expression on its member channel is sent per note:
+//!
+//! - `/mpe/<note ID>/on <note> <velocity>`
+//! - `/mpe/<note ID>/off <velocity>`
+//! - `/mpe/<note ID>/bend <value>`, where 0.5 means no bend.
+//! - `/mpe/<note ID>/pressure <pressure>`
+//! - `/mpe/<note ID>/timbre <value>`, from CC 74.
+//!
+//! This assumes the lower zone, so the first MIDI channel is the master channel and its messages
+//! aren't per note.
+//!
+//! Hosts with CLAP polyphonic modulation or note expressions, like Bitwig, also send per-voice
+//! data, which is sent under the voice's ID. Voices without a host-assigned ID use
+//! `<channel> * 128 + <note>`.
+//!
+//! - `/voice/<voice ID>/on <channel> <note> <velocity>` and `/voice/<voice ID>/off <velocity>`,
+//!   only for notes the host assigned an ID to.
+//! - `/voice/<voice ID>/channel/<index> <offset>` for polyphonic modulation of a channel, with the
+//!   channel's index counting from zero and the offset in normalized units.
+//! - `/voice/<voice ID>/<expression> <value>` for the `pressure`, `volume`, `pan`, `tuning`,
+//!   `vibrato`, `expression` and `brightness` note expressions, in the units nih-plug reports them
+//!   in.
+//!
+//! The messages in the first list can also be sent to the receive port, which turns them into MIDI
+//! on the plugin's MIDI output so an OSC control surface can drive the plugins after this one.
+
+use nannou_osc as osc;
+use nih_plug::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::receiver::arg_to_f32;
+
+/// The number of voices hosts may send polyphonic modulation for at once.
+pub const MAX_VOICES: u32 = 128;
+/// How many MIDI messages received over OSC can wait for the audio thread to send them.
+pub const OUTPUT_QUEUE_LENGTH: usize = 1024;
+
+/// A MIDI event that's forwarded over OSC. This is small enough to be sent to the background task
+/// as is.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MidiMessage {
+    NoteOn {
+        channel: u8,
+        note: u8,
+        velocity: f32,
+    },
+    NoteOff {
+        channel: u8,
+        note: u8,
+        velocity: f32,
+    },
+    Cc {
+        channel: u8,
+        cc: u8,
+        value: f32,
+    },
+    PitchBend {
+        channel: u8,
+        value: f32,
+    },
+    ChannelPressure {
+        channel: u8,
+        pressure: f32,
+    },
+    ProgramChange {
+        channel: u8,
+        program: u8,
+    },
+    Mpe {
+        note_id: u32,
+        expression: MpeExpression,
+    },
+    Voice {
+        voice_id: i32,
+        expression: VoiceExpression,
+    },
+}
+
+/// A MIDI CC on a specific MIDI channel, which channels can be bound to through MIDI learn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MidiCc {
+    pub channel: u8,
+    pub cc: u8,
+}
+
+impl fmt::Display for MidiCc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CC {} on MIDI channel {}", self.cc, self.channel + 1)
+    }
+}
+
+/// Per-note messages for MPE, see the module documentation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MpeExpression {
+    On { note: u8, velocity: f32 },
+    Off { velocity: f32 },
+    Bend(f32),
+    Pressure(f32),
+    Timbre(f32),
+}
+
+/// Per-voice messages for CLAP polyphonic modulation and note expressions, see the module
+/// documentation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VoiceExpression {
+    On {
+        channel: u8,
+        note: u8,
+        velocity: f32,
+    },
+    Off {
+        velocity: f32,
+    },
+    /// A channel's polyphonic modulation offset for this voice.
+    Modulation {
+        index: usize,
+        offset: f32,
+    },
+    Pressure(f32),
+    Volume(f32),
+    Pan(f32),
+    Tuning(f32),
+    Vibrato(f32),
+    Expression(f32),
+    Brightness(f32),
+}
+
+/// The MIDI channel used for zone-wide messages in the MPE lower zone.
+const MPE_MASTER_CHANNEL: u8 = 0;
+/// The CC MPE controllers send their third dimension of expression on.
+const MPE_TIMBRE_CC: u8 = 74;
+
+/// Keeps track of which note is playing on each MPE member channel. This lives on the audio thread.
+#[derive(Debug, Default)]
+pub struct MpeTracker {
+    /// The ID and note number of the note playing on each MIDI channel.
+    notes: [Option<(u32, u8)>; 16],
+    next_note_id: u32,
+}
+
+impl MpeTracker {
+    /// The per-note message for a MIDI event, if it applies to a note playing on a member channel.
+    pub fn process(&mut self, midi: MidiMessage) -> Option<MidiMessage> {
+        let (channel, expression) = match midi {
+            MidiMessage::NoteOn {
+                channel,
+                note,
+                velocity,
+            } if channel != MPE_MASTER_CHANNEL => {
+                let note_id = self.next_note_id;
+                self.next_note_id = self.next_note_id.wrapping_add(1);
+                *self.notes.get_mut(channel as usize)? = Some((note_id, note));
+
+                return Some(MidiMessage::Mpe {
+                    note_id,
+                    expression: MpeExpression::On { note, velocity },
+                });
+            }
+            MidiMessage::NoteOff {
+                channel,
+                note,
+                velocity,
+            } => {
+                let slot = self.notes.get_mut(channel as usize)?;
+                let (note_id, playing) = (*slot)?;
+                if playing != note {
+                    return None;
+                }
+                *slot = None;
+
+                return Some(MidiMessage::Mpe {
+                    note_id,
+                    expression: MpeExpression::Off { velocity },
+                });
+            }
+            MidiMessage::PitchBend { channel, value } => (channel, MpeExpression::Bend(value)),
+            MidiMessage::ChannelPressure { channel, pressure } => {
+                (channel, MpeExpression::Pressure(pressure))
+            }
+            MidiMessage::Cc { channel, cc, value } if cc == MPE_TIMBRE_CC => {
+                (channel, MpeExpression::Timbre(value))
+            }
+            _ => return None,
+        };
+
+        let (note_id, _) = (*self.notes.get(channel as usize)?)?;
+        Some(MidiMessage::Mpe {
+            note_id,
+            expression,
+        })
+    }
+
+    pub fn reset(&mut self) {
+        self.notes = [None; 16];
+    }
+}
+
+impl MidiMessage {
+    /// The message to forward for a note event, if it's one that gets forwarded.
+    pub fn from_event(event: NoteEvent) -> Option<Self> {
+        match event {
+            NoteEvent::NoteOn {
+                channel,
+                note,
+                velocity,
+                ..
+            } => Some(Self::NoteOn {
+                channel,
+                note,
+                velocity,
+            }),
+            NoteEvent::NoteOff {
+                channel,
+                note,
+                velocity,
+                ..
+            } => Some(Self::NoteOff {
+                channel,
+                note,
+                velocity,
+            }),
+            NoteEvent::MidiCC {
+                channel, cc, value, ..
+            } => Some(Self::Cc { channel, cc, value }),
+            NoteEvent::MidiPitchBend { channel, value, .. } => {
+                Some(Self::PitchBend { channel, value })
+            }
+            NoteEvent::MidiChannelPressure {
+                channel, pressure, ..
+            } => Some(Self::ChannelPressure { channel, pressure }),
+            NoteEvent::MidiProgramChange {
+                channel, program, ..
+            } => Some(Self::ProgramChange { channel, program }),
+            _ => None,
+        }
+    }
+
+    /// The per-voice message for a note event, if it has one. This is forwarded in addition to the
+    /// message from [`from_event()`][Self::from_event()].
+    pub fn from_voice_event(event: NoteEvent) -> Option<Self> {
+        let voice = |voice_id: Option<i32>, channel: u8, note: u8, expression| Self::Voice {
+            voice_id: voice_id.unwrap_or(channel as i32 * 128 + note as i32),
+            expression,
+        };
+
+        match event {
+            NoteEvent::NoteOn {
+                voice_id: Some(voice_id),
+                channel,
+                note,
+                velocity,
+                ..
+            } => Some(Self::Voice {
+                voice_id,
+                expression: VoiceExpression::On {
+                    channel,
+                    note,
+                    velocity,
+                },
+            }),
+            NoteEvent::NoteOff {
+                voice_id: Some(voice_id),
+                velocity,
+                ..
+            } => Some(Self::Voice {
+                voice_id,
+                expression: VoiceExpression::Off { velocity },
+            }),
+            NoteEvent::PolyModulation {
+                voice_id,
+                poly_modulation_id,
+                normalized_offset,
+                ..
+            } => Some(Self::Voice {
+                voice_id,
+                expression: VoiceExpression::Modulation {
+                    index: poly_modulation_id as usize,
+                    offset: normalized_offset,
+                },
+            }),
+            NoteEvent::PolyPressure {
+                voice_id,
+                channel,
+                note,
+                pressure,
+                ..
+            } => Some(voice(
+                voice_id,
+                channel,
+                note,
+                VoiceExpression::Pressure(pressure),
+            )),
+            NoteEvent::PolyVolume {
+                voice_id,
+                channel,
+                note,
+                gain,
+                ..
+            } => Some(voice(
+                voice_id,
+                channel,
+                note,
+                VoiceExpression::Volume(gain),
+            )),
+            NoteEvent::PolyPan {
+                voice_id,
+                channel,
+                note,
+                pan,
+                ..
+            } => Some(voice(voice_id, channel, note, VoiceExpression::Pan(pan))),
+            NoteEvent::PolyTuning {
+                voice_id,
+                channel,
+                note,
+                tuning,
+                ..
+            } => Some(voice(
+                voice_id,
+                channel,
+                note,
+                VoiceExpression::Tuning(tuning),
+            )),
+            NoteEvent::PolyVibrato {
+                voice_id,
+                channel,
+                note,
+                vibrato,
+                ..
+            } => Some(voice(
+                voice_id,
+                channel,
+                note,
+                VoiceExpression::Vibrato(vibrato),
+            )),
+            NoteEvent::PolyExpression {
+                voice_id,
+                channel,
+                note,
+                expression,
+                ..
+            } => Some(voice(
+                voice_id,
+                channel,
+                note,
+                VoiceExpression::Expression(expression),
+            )),
+            NoteEvent::PolyBrightness {
+                voice_id,
+                channel,
+                note,
+                brightness,
+                ..
+            } => Some(voice(
+                voice_id,
+                channel,
+                note,
+                VoiceExpression::Brightness(brightness),
+            )),
+            _ => None,
+        }
+    }
+
+    /// Parse a MIDI message sent to the receive port. `addr` doesn't include the namespace.
+    pub fn from_osc(addr: &str, args: &[osc::Type]) -> Option<Self> {
+        let args: Vec<f32> = args.iter().map(arg_to_f32).collect::<Option<_>>()?;
+        let channel = |value: f32| (0.0..16.0).contains(&value).then_some(value as u8);
+        let data_byte = |value: f32| (0.0..128.0).contains(&value).then_some(value as u8);
+        let normalized = |value: f32| value.clamp(0.0, 1.0);
+
+        match (addr, args.as_slice()) {
+            ("/note/on", &[ch, note, velocity]) => Some(Self::NoteOn {
+                channel: channel(ch)?,
+                note: data_byte(note)?,
+                velocity: normalized(velocity),
+            }),
+            ("/note/off", &[ch, note, velocity]) => Some(Self::NoteOff {
+                channel: channel(ch)?,
+                note: data_byte(note)?,
+                velocity: normalized(velocity),
+            }),
+            ("/bend", &[ch, value]) => Some(Self::PitchBend {
+                channel: channel(ch)?,
+                value: normalized(value),
+            }),
+            ("/pressure", &[ch, pressure]) => Some(Self::ChannelPressure {
+                channel: channel(ch)?,
+                pressure: normalized(pressure),
+            }),
+            ("/program", &[ch, program]) => Some(Self::ProgramChange {
+                channel: channel(ch)?,
+                program: data_byte(program)?,
+            }),
+            (addr, &[ch, value]) => Some(Self::Cc {
+                channel: channel(ch)?,
+                cc: addr
+                    .strip_prefix("/cc/")?
+                    .parse()
+                    .ok()
+                    .filter(|&cc| cc < 128)?,
+                value: normalized(value),
+            }),
+            _ => None,
+        }
+    }
+
+    /// The event to send to the plugin's MIDI output. Per-note MPE messages aren't sent.
+    pub fn to_event(self, timing: u32) -> Option<NoteEvent> {
+        match self {
+            Self::NoteOn {
+                channel,
+                note,
+                velocity,
+            } => Some(NoteEvent::NoteOn {
+                timing,
+                voice_id: None,
+                channel,
+                note,
+                velocity,
+            }),
+            Self::NoteOff {
+                channel,
+                note,
+                velocity,
+            } => Some(NoteEvent::NoteOff {
+                timing,
+                voice_id: None,
+                channel,
+                note,
+                velocity,
+            }),
+            Self::Cc { channel, cc, value } => Some(NoteEvent::MidiCC {
+                timing,
+                channel,
+                cc,
+                value,
+            }),
+            Self::PitchBend { channel, value } => Some(NoteEvent::MidiPitchBend {
+                timing,
+                channel,
+                value,
+            }),
+            Self::ChannelPressure { channel, pressure } => Some(NoteEvent::MidiChannelPressure {
+                timing,
+                channel,
+                pressure,
+            }),
+            Self::ProgramChange { channel, program } => Some(NoteEvent::MidiProgramChange {
+                timing,
+                channel,
+                program,
+            }),
+            Self::Mpe { .. } | Self::Voice { .. } => None,
+        }
+    }
+
+    /// The OSC address, without the namespace, and arguments for this message.
+    pub fn to_osc(self) -> (String, Vec<osc::Type>) {
+        match self {
+            Self::NoteOn {
+                channel,
+                note,
+                velocity,
+            } => note_message("/note/on", channel, note, velocity),
+            Self::NoteOff {
+                channel,
+                note,
+                velocity,
+            } => note_message("/note/off", channel, note, velocity),
+            Self::Cc { channel, cc, value } => (
+                format!("/cc/{cc}"),
+                vec![osc::Type::Int(channel as i32), osc::Type::Float(value)],
+            ),
+            Self::PitchBend { channel, value } => (
+                String::from("/bend"),
+                vec![osc::Type::Int(channel as i32), osc::Type::Float(value)],
+            ),
+            Self::ChannelPressure { channel, pressure } => (
+                String::from("/pressure"),
+                vec![osc::Type::Int(channel as i32), osc::Type::Float(pressure)],
+            ),
+            Self::ProgramChange { channel, program } => (
+                String::from("/program"),
+                vec![
+                    osc::Type::Int(channel as i32),
+                    osc::Type::Int(program as i32),
+                ],
+            ),
+            Self::Mpe {
+                note_id,
+                expression,
+            } => {
+                let (name, args) = match expression {
+                    MpeExpression::On { note, velocity } => (
+                        "on",
+                        vec![osc::Type::Int(note as i32), osc::Type::Float(velocity)],
+                    ),
+                    MpeExpression::Off { velocity } => ("off", vec![osc::Type::Float(velocity)]),
+                    MpeExpression::Bend(value) => ("bend", vec![osc::Type::Float(value)]),
+                    MpeExpression::Pressure(value) => ("pressure", vec![osc::Type::Float(value)]),
+                    MpeExpression::Timbre(value) => ("timbre", vec![osc::Type::Float(value)]),
+                };
+
+                (format!("/mpe/{note_id}/{name}"), args)
+            }
+            Self::Voice {
+                voice_id,
+                expression,
+            } => {
+                let (name, value) = match expression {
+                    VoiceExpression::On {
+                        channel,
+                        note,
+                        velocity,
+                    } => {
+                        let args = vec![
+                            osc::Type::Int(channel as i32),
+                            osc::Type::Int(note as i32),
+                            osc::Type::Float(velocity),
+                        ];
+                        return (format!("/voice/{voice_id}/on"), args);
+                    }
+                    VoiceExpression::Off { velocity } => ("off", velocity),
+                    VoiceExpression::Modulation { index, offset } => {
+                        let args = vec![osc::Type::Float(offset)];
+                        return (format!("/voice/{voice_id}/channel/{index}"), args);
+                    }
+                    VoiceExpression::Pressure(value) => ("pressure", value),
+                    VoiceExpression::Volume(value) => ("volume", value),
+                    VoiceExpression::Pan(value) => ("pan", value),
+                    VoiceExpression::Tuning(value) => ("tuning", value),
+                    VoiceExpression::Vibrato(value) => ("vibrato", value),
+                    VoiceExpression::Expression(value) => ("expression", value),
+                    VoiceExpression::Brightness(value) => ("brightness", value),
+                };
+
+                (
+                    format!("/voice/{voice_id}/{name}"),
+                    vec![osc::Type::Float(value)],
+                )
+            }
+        }
+    }
+}
+
+fn note_message(addr: &str, channel: u8, note: u8, velocity: f32) -> (String, Vec<osc::Type>) {
+    (
+        String::from(addr),
+        vec![
+            osc::Type::Int(channel as i32),
+            osc::Type::Int(note as i32),
+            osc::Type::Float(velocity),
+        ],
+    )
+}
@@ -0,0 +1,189 @@
+//! Brings state saved by older versions of the plugin up to date, so changes to how settings are
+//! stored don't silently reset a show that was saved before them. Every saved state includes the
+//! [`CURRENT_VERSION`] it was saved with, and [`migrate()`] runs after a state has been restored.
+//!
+//! Settings that were replaced are kept as persisted fields under their old keys, wrapped in an
+//! `Option`. States saved since then store `null` for them, so they're only ever set when an old
+//! state is restored.
+//!
+//! A state without a version can't be told apart from the plugin being reactivated without
+//! restoring anything, so every migration needs to leave state in the current format untouched.
+
+use nih_plug::params::persist::PersistentField;
+use nih_plug::prelude::*;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use crate::destination::Destination;
+use crate::{normalize_namespace, ChannelConfig, SpaceRadioParams, NUM_CHANNELS};
+
+/// The version of the state format. Increment this when adding a migration.
+///
+/// 1. A list of destinations replaced the `osc_address`, `osc_port`, `protocol`, and `tcp_framing`
+///    settings.
+pub const CURRENT_VERSION: u32 = 1;
+/// The version of states saved before states were versioned.
+const UNVERSIONED: u32 = 0;
+
+/// The version of the state that was restored last. This is always saved as [`CURRENT_VERSION`]
+/// since the state is migrated as soon as it's restored.
+pub struct StateVersion {
+    restored: AtomicU32,
+}
+
+impl Default for StateVersion {
+    fn default() -> Self {
+        Self {
+            restored: AtomicU32::new(UNVERSIONED),
+        }
+    }
+}
+
+impl StateVersion {
+    /// The version of the state that was restored since the last call, if there was one.
+    fn take(&self) -> u32 {
+        self.restored.swap(UNVERSIONED, Ordering::Relaxed)
+    }
+}
+
+impl<'a> PersistentField<'a, u32> for StateVersion {
+    fn set(&self, new_value: u32) {
+        self.restored.store(new_value, Ordering::Relaxed);
+    }
+
+    fn map<F, R>(&self, f: F) -> R
+    where
+        F: Fn(&u32) -> R,
+    {
+        f(&CURRENT_VERSION)
+    }
+}
+
+/// Migrate the state that was just restored. This is called from `initialize()`, which the wrapper
+/// also calls after restoring state.
+pub(crate) fn migrate(params: &SpaceRadioParams) {
+    let version = params.state_version.take();
+    if version > CURRENT_VERSION {
+        nih_warn!(
+            "The state was saved by a newer version of the plugin (format {version}, this version \
+             uses {CURRENT_VERSION}), some settings may not have been restored"
+        );
+    }
+
+    if version < 1 {
+        migrate_destination(params);
+    }
+
+    // Older versions stored the namespace as it was typed
+    let mut namespace = params.osc_namespace.write().unwrap();
+    *namespace = normalize_namespace(&namespace);
+    drop(namespace);
+
+    // State saved by versions with fewer channels has no settings for the rest of them
+    let mut channels = params.channels.write().unwrap();
+    if channels.len() < NUM_CHANNELS {
+        channels.resize(NUM_CHANNELS, ChannelConfig::default());
+    }
+}
+
+/// Turn the single destination from before version 1 into the destination list.
+fn migrate_destination(params: &SpaceRadioParams) {
+    let address = params.legacy_osc_address.write().unwrap().take();
+    let port = params.legacy_osc_port.write().unwrap().take();
+    let protocol = params.legacy_protocol.write().unwrap().take();
+    let framing = params.legacy_tcp_framing.write().unwrap().take();
+    if address.is_none() && port.is_none() {
+        return;
+    }
+
+    let default = Destination::default();
+    let destination = Destination {
+        address: address.unwrap_or(default.address),
+        port: port.unwrap_or(default.port),
+        protocol: protocol.unwrap_or(default.protocol),
+        framing: framing.unwrap_or(default.framing),
+        ..default
+    };
+    nih_log!(
+        "Migrated the destination {} from an older state",
+        destination.target()
+    );
+    *params.destinations.write().unwrap() = vec![destination];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::changes::ChangeLog;
+    use crate::dirty::DirtyFlags;
+    use crate::socket::{Framing, Protocol};
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+
+    fn params() -> SpaceRadioParams {
+        let flags = || Arc::new(DirtyFlags::new(NUM_CHANNELS));
+        SpaceRadioParams::new(
+            &flags(),
+            &Arc::new(ChangeLog::new(NUM_CHANNELS)),
+            &flags(),
+            &flags(),
+            &Arc::new(AtomicBool::new(false)),
+        )
+    }
+
+    #[test]
+    fn converts_the_legacy_destination() {
+        let params = params();
+        *params.legacy_osc_address.write().unwrap() = Some(String::from("10.0.0.2"));
+        *params.legacy_osc_port.write().unwrap() = Some(8000);
+        *params.legacy_protocol.write().unwrap() = Some(Protocol::Tcp);
+        *params.legacy_tcp_framing.write().unwrap() = Some(Framing::LengthPrefixed);
+
+        migrate(&params);
+
+        assert_eq!(
+            *params.destinations.read().unwrap(),
+            [Destination {
+                address: String::from("10.0.0.2"),
+                port: 8000,
+                protocol: Protocol::Tcp,
+                framing: Framing::LengthPrefixed,
+                ..Destination::default()
+            }]
+        );
+        assert_eq!(*params.legacy_osc_address.read().unwrap(), None);
+        assert_eq!(*params.legacy_osc_port.read().unwrap(), None);
+        assert_eq!(*params.legacy_protocol.read().unwrap(), None);
+        assert_eq!(*params.legacy_tcp_framing.read().unwrap(), None);
+    }
+
+    #[test]
+    fn leaves_current_states_untouched() {
+        let destinations = vec![
+            Destination {
+                address: String::from("10.0.0.2"),
+                ..Destination::default()
+            },
+            Destination {
+                address: String::from("10.0.0.3"),
+                port: 8000,
+                ..Destination::default()
+            },
+        ];
+
+        // Once with a restored state, and once for a reactivation without one
+        for restored in [true, false] {
+            let params = params();
+            *params.destinations.write().unwrap() = destinations.clone();
+            *params.osc_namespace.write().unwrap() = String::from("/radio");
+            if restored {
+                params.state_version.set(CURRENT_VERSION);
+            }
+
+            migrate(&params);
+
+            assert_eq!(*params.destinations.read().unwrap(), destinations);
+            assert_eq!(*params.osc_namespace.read().unwrap(), "/radio");
+            assert_eq!(params.channels.read().unwrap().len(), NUM_CHANNELS);
+        }
+    }
+}
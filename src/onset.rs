@@ -0,0 +1,77 @@
+//! Onset detection with spectral flux, so lighting and visuals can flash exactly on drum hits
+//! rather than relying on coarse level thresholds. This runs on the analysis thread, see the
+//! `analysis` module.
+//!
+//! - `/onset <strength>` for every detected onset. The strength is the onset's spectral flux, the
+//!   summed increase in amplitude over all frequencies. Louder and more broadband hits are
+//!   stronger.
+
+use std::time::Duration;
+
+/// The number of previous frames the adaptive threshold is based on.
+const HISTORY_LENGTH: usize = 16;
+/// Flux below this is never an onset, so silence with a bit of noise doesn't trigger anything.
+const MIN_FLUX: f32 = 0.01;
+/// The shortest time between two onsets.
+const MIN_ONSET_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Default)]
+pub struct OnsetDetector {
+    /// The previous frame's amplitude per frequency bin.
+    previous: Vec<f32>,
+    /// The flux of the most recent frames, as a ring buffer.
+    history: [f32; HISTORY_LENGTH],
+    next_history: usize,
+    previous_flux: f32,
+    /// The time since the last onset.
+    since_onset: Duration,
+}
+
+impl OnsetDetector {
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Process the next frame's amplitudes, `hop` after the previous frame. Returns the onset's
+    /// strength if the previous frame was an onset. An onset is a peak in the flux that's
+    /// `sensitivity` times larger than the recent average.
+    pub fn process(
+        &mut self,
+        magnitudes: impl Iterator<Item = f32>,
+        hop: Duration,
+        sensitivity: f32,
+    ) -> Option<f32> {
+        let mut flux = 0.0;
+        let mut num_bins = 0;
+        for (bin_idx, magnitude) in magnitudes.enumerate() {
+            match self.previous.get_mut(bin_idx) {
+                Some(previous) => {
+                    flux += (magnitude - *previous).max(0.0);
+                    *previous = magnitude;
+                }
+                None => self.previous.push(magnitude),
+            }
+            num_bins += 1;
+        }
+        self.previous.truncate(num_bins);
+        self.since_onset += hop;
+
+        // The previous frame was a peak if the flux has started dropping again
+        let average = self.history.iter().sum::<f32>() / HISTORY_LENGTH as f32;
+        let peak = self.previous_flux;
+        let onset = peak > flux
+            && peak >= MIN_FLUX
+            && peak > average * sensitivity
+            && self.since_onset >= MIN_ONSET_INTERVAL;
+
+        self.history[self.next_history] = flux;
+        self.next_history = (self.next_history + 1) % HISTORY_LENGTH;
+        self.previous_flux = flux;
+        if onset {
+            self.since_onset = Duration::ZERO;
+            Some(peak)
+        } else {
+            None
+        }
+    }
+}
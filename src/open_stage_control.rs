@@ -0,0 +1,95 @@
+//! Generates an Open Stage Control session with a fader for every channel in use, so a browser
+//! based control surface can drive the plugin through its receive port. Every group gets its own
+//! tab, and the faders send the channel's own address in the range the channel sends in, which is
+//! what the receiver expects.
+
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+
+use crate::{channel_groups, ChannelConfig, SpaceRadioParams};
+
+/// The Open Stage Control version whose session format this follows.
+const SESSION_VERSION: &str = "1.26.2";
+/// The faders' width in pixels. Tabs lay their faders out from left to right.
+const FADER_WIDTH: u32 = 60;
+
+/// Write the session for the current channel configuration to `path`. The faders send to the
+/// receive port on this machine, so this fails if the receiver is disabled.
+pub(crate) fn export(params: &SpaceRadioParams, path: &Path) -> Result<(), String> {
+    let receive_port = *params.receive_port.read().unwrap();
+    if receive_port == 0 {
+        return Err(String::from(
+            "The receive port needs to be set for the faders to have somewhere to send to",
+        ));
+    }
+
+    let json = serde_json::to_string_pretty(&session(params, receive_port))
+        .map_err(|err| format!("Could not serialize the session: {err}"))?;
+    fs::write(path, json).map_err(|err| format!("Could not write {}: {err}", path.display()))
+}
+
+fn session(params: &SpaceRadioParams, receive_port: u16) -> Value {
+    let profile = params.active_profile();
+    let channels = params.channels.read().unwrap();
+    let default_channel = ChannelConfig::default();
+    let namespace = params.osc_namespace.read().unwrap();
+    let namespace = namespace.trim_end_matches('/');
+    let target = format!("127.0.0.1:{receive_port}");
+
+    let tabs: Vec<Value> = channel_groups(&channels, params.channel_count())
+        .into_iter()
+        .enumerate()
+        .map(|(tab, (group, members))| {
+            let widgets: Vec<Value> = members
+                .into_iter()
+                .map(|index| {
+                    let channel = channels.get(index).unwrap_or(&default_channel);
+                    let address = format!(
+                        "{namespace}{}",
+                        params.channel_address(index, &channels, &profile)
+                    );
+                    let label = channel
+                        .name
+                        .clone()
+                        .unwrap_or_else(|| format!("Ch. {}", index + 1));
+                    let (min, max) = channel.output_range(&profile);
+
+                    json!({
+                        "type": "fader",
+                        "id": format!("channel_{}", index + 1),
+                        "label": label,
+                        "width": FADER_WIDTH,
+                        "expand": false,
+                        "range": { "min": min, "max": max },
+                        "address": address,
+                        "preArgs": [],
+                        "target": [target],
+                        "bypass": false,
+                    })
+                })
+                .collect();
+
+            json!({
+                "type": "tab",
+                "id": format!("tab_{}", tab + 1),
+                "label": group.unwrap_or("Channels"),
+                "layout": "horizontal",
+                "widgets": widgets,
+                "tabs": [],
+            })
+        })
+        .collect();
+
+    json!({
+        "createdWith": "Open Stage Control",
+        "version": SESSION_VERSION,
+        "type": "session",
+        "content": {
+            "type": "root",
+            "id": "root",
+            "widgets": [],
+            "tabs": tabs,
+        },
+    })
+}
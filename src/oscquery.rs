@@ -0,0 +1,249 @@
+//! A minimal OSCQuery server, so clients like Vezér and Open Stage Control can browse the
+//! channels' addresses, types, and ranges instead of having them typed in by hand. Only the HTTP
+//! part of the protocol is implemented. The optional WebSocket extension for streaming values is
+//! not, and `HOST_INFO` tells clients as much so they'll poll instead.
+
+use nannou_osc as osc;
+use nih_plug::prelude::*;
+use serde_json::{json, Value};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::{ChannelConfig, SpaceRadio, SpaceRadioParams};
+
+/// How often the server checks for new connections and changed settings.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+const READ_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Stops and joins its thread when dropped.
+pub struct OscQueryServer {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl OscQueryServer {
+    /// The server starts and stops along with the `oscquery_enabled` setting, so it can be toggled
+    /// without reloading the plugin.
+    pub(crate) fn spawn(params: Arc<SpaceRadioParams>) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = {
+            let stop = Arc::clone(&stop);
+            thread::Builder::new()
+                .name(String::from("space-radio-oscquery"))
+                .spawn(move || run(&params, &stop))
+                .ok()
+        };
+
+        Self { stop, handle }
+    }
+}
+
+impl Drop for OscQueryServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn run(params: &SpaceRadioParams, stop: &AtomicBool) {
+    // The listener and the port it was bound for, or the port that failed to bind so it isn't
+    // retried until the setting changes
+    let mut listener: Option<(TcpListener, u16)> = None;
+    let mut failed_port = None;
+
+    while !stop.load(Ordering::Relaxed) {
+        let port = (*params.oscquery_enabled.read().unwrap())
+            .then(|| *params.oscquery_port.read().unwrap());
+        if listener.as_ref().map(|(_, bound_port)| *bound_port) != port {
+            listener = None;
+            if let Some(port) = port.filter(|port| failed_port != Some(*port)) {
+                match bind(port) {
+                    Ok(new_listener) => {
+                        if let Ok(addr) = new_listener.local_addr() {
+                            nih_log!("Serving OSCQuery on {addr}");
+                        }
+                        failed_port = None;
+                        listener = Some((new_listener, port));
+                    }
+                    Err(err) => {
+                        nih_error!("Could not start the OSCQuery server on port {port}: {err}");
+                        failed_port = Some(port);
+                    }
+                }
+            }
+        }
+
+        if let Some((listener, _)) = &listener {
+            loop {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        if let Err(err) = handle_connection(params, stream) {
+                            nih_warn!("Could not answer an OSCQuery request: {err}");
+                        }
+                    }
+                    Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                    Err(err) => {
+                        nih_warn!("Could not accept an OSCQuery connection: {err}");
+                        break;
+                    }
+                }
+            }
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+fn bind(port: u16) -> io::Result<TcpListener> {
+    let listener = TcpListener::bind(SocketAddr::from(([0, 0, 0, 0], port)))?;
+    // Polling keeps the thread responsive to the stop flag and to settings changes
+    listener.set_nonblocking(true)?;
+
+    Ok(listener)
+}
+
+fn handle_connection(params: &SpaceRadioParams, stream: TcpStream) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+
+    let mut request_line = String::new();
+    let mut reader = BufReader::new(&stream);
+    reader.read_line(&mut request_line)?;
+    // The headers aren't needed, but they should be read before the connection gets closed
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = (parts.next(), parts.next().unwrap_or("/"));
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+
+    let response = match (method, query) {
+        (Some("GET"), "HOST_INFO") => Some(host_info()),
+        (Some("GET"), _) => find(namespace(params), path),
+        _ => None,
+    };
+
+    let mut stream = stream;
+    match response {
+        Some(body) => {
+            let body = body.to_string();
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+                 Connection: close\r\n\r\n{body}",
+                body.len()
+            )
+        }
+        None => write!(
+            stream,
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+        ),
+    }
+}
+
+/// The plugin doesn't listen for OSC itself, so there's no `OSC_PORT` to advertise.
+fn host_info() -> Value {
+    json!({
+        "NAME": SpaceRadio::NAME,
+        "EXTENSIONS": {
+            "ACCESS": true,
+            "VALUE": true,
+            "RANGE": true,
+            "DESCRIPTION": true,
+            "TYPE": true,
+            "LISTEN": false,
+            "PATH_CHANGED": false,
+        },
+    })
+}
+
+/// The full address tree. Every channel is a read-only leaf with its current value.
+fn namespace(params: &SpaceRadioParams) -> Value {
+    let profile = params.active_profile();
+    let channels = params.channels.read().unwrap();
+    let default_channel = ChannelConfig::default();
+    let namespace = params.osc_namespace.read().unwrap();
+    let namespace = namespace.trim_end_matches('/');
+
+    let mut root = container("/");
+    for index in 0..params.channel_count() {
+        let channel = channels.get(index).unwrap_or(&default_channel);
+        let param = &params.array_params[index].val;
+        let address = format!(
+            "{namespace}{}",
+            params.channel_address(index, &channels, &profile)
+        );
+        let (min, max) = channel.output_range(&profile);
+        let value = channel.encode(channel.transform(param.value(), &profile), &profile);
+        let (type_tag, value) = match value {
+            osc::Type::Int(value) => ("i", json!(value)),
+            osc::Type::Bool(value) => (if value { "T" } else { "F" }, json!(value)),
+            osc::Type::Float(value) if value.is_finite() => ("f", json!(value)),
+            _ => ("f", Value::Null),
+        };
+
+        insert(
+            &mut root,
+            &address,
+            json!({
+                "FULL_PATH": address,
+                "TYPE": type_tag,
+                "VALUE": [value],
+                "RANGE": [{ "MIN": min, "MAX": max }],
+                "ACCESS": 1,
+                "DESCRIPTION": param.name(),
+            }),
+        );
+    }
+
+    root
+}
+
+fn container(full_path: &str) -> Value {
+    json!({ "FULL_PATH": full_path, "CONTENTS": {} })
+}
+
+/// Insert a leaf node, creating the containers along its path.
+fn insert(root: &mut Value, address: &str, leaf: Value) {
+    let segments: Vec<&str> = address
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect();
+    let Some((last, parents)) = segments.split_last() else {
+        return;
+    };
+
+    let mut node = root;
+    let mut full_path = String::new();
+    for segment in parents {
+        full_path.push('/');
+        full_path.push_str(segment);
+
+        node = &mut node["CONTENTS"][*segment];
+        if node.is_null() {
+            *node = container(&full_path);
+        }
+    }
+    node["CONTENTS"][*last] = leaf;
+}
+
+/// Look up the node at `path`.
+fn find(root: Value, path: &str) -> Option<Value> {
+    path.split('/')
+        .filter(|segment| !segment.is_empty())
+        .try_fold(root, |node, segment| match node {
+            Value::Object(mut node) => match node.remove("CONTENTS") {
+                Some(Value::Object(mut contents)) => contents.remove(segment),
+                _ => None,
+            },
+            _ => None,
+        })
+}
@@ -0,0 +1,108 @@
+//! Monophonic pitch tracking with the YIN algorithm, for pitch-reactive visuals and tuners driven
+//! straight from a DAW channel. This runs on the analysis thread, see the `analysis` module.
+//!
+//! - `/pitch/hz <frequency>`
+//! - `/pitch/confidence <confidence>` between zero and one. Unpitched input like noise or silence
+//!   has a low confidence, and its frequency shouldn't be relied upon.
+
+use nannou_osc as osc;
+
+/// YIN's threshold for the first dip in the normalized difference function that's considered the
+/// period.
+const THRESHOLD: f32 = 0.15;
+/// Frames quieter than this, as a mean square, are considered silent.
+const SILENCE: f32 = 1e-8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pitch {
+    pub hz: f32,
+    pub confidence: f32,
+}
+
+impl Pitch {
+    pub fn to_osc(self) -> [(&'static str, Vec<osc::Type>); 2] {
+        [
+            ("/pitch/hz", vec![osc::Type::Float(self.hz)]),
+            ("/pitch/confidence", vec![osc::Type::Float(self.confidence)]),
+        ]
+    }
+}
+
+/// Tracks the pitch of a frame of input. The lowest detectable frequency is the sample rate
+/// divided by half the frame's length.
+#[derive(Default)]
+pub struct PitchTracker {
+    /// YIN's cumulative mean normalized difference for every lag, preallocated.
+    difference: Vec<f32>,
+}
+
+impl PitchTracker {
+    pub fn process(&mut self, frame: &[f32], sample_rate: f32) -> Pitch {
+        let window = frame.len() / 2;
+        let energy = frame[..window]
+            .iter()
+            .map(|sample| sample * sample)
+            .sum::<f32>();
+        if window < 2 || energy / (window as f32) < SILENCE {
+            return Pitch {
+                hz: 0.0,
+                confidence: 0.0,
+            };
+        }
+
+        self.difference.clear();
+        self.difference.push(1.0);
+        let mut running_sum = 0.0;
+        for lag in 1..window {
+            let difference: f32 = frame[..window]
+                .iter()
+                .zip(&frame[lag..lag + window])
+                .map(|(a, b)| (a - b) * (a - b))
+                .sum();
+            running_sum += difference;
+            self.difference.push(if running_sum > 0.0 {
+                difference * lag as f32 / running_sum
+            } else {
+                1.0
+            });
+        }
+
+        // The first dip below the threshold, followed down to its minimum. If there isn't one the
+        // input isn't very periodic, and the global minimum is the best guess.
+        let lag = match (2..window).find(|&lag| self.difference[lag] < THRESHOLD) {
+            Some(mut lag) => {
+                while lag + 1 < window && self.difference[lag + 1] < self.difference[lag] {
+                    lag += 1;
+                }
+                lag
+            }
+            None => (2..window)
+                .min_by(|&a, &b| self.difference[a].total_cmp(&self.difference[b]))
+                .unwrap_or(2),
+        };
+
+        Pitch {
+            hz: sample_rate / self.interpolate(lag),
+            confidence: (1.0 - self.difference[lag]).clamp(0.0, 1.0),
+        }
+    }
+
+    /// Refine a lag with parabolic interpolation between its neighbours.
+    fn interpolate(&self, lag: usize) -> f32 {
+        if lag + 1 >= self.difference.len() {
+            return lag as f32;
+        }
+
+        let (before, at, after) = (
+            self.difference[lag - 1],
+            self.difference[lag],
+            self.difference[lag + 1],
+        );
+        let curvature = before - 2.0 * at + after;
+        if curvature.abs() < f32::EPSILON {
+            lag as f32
+        } else {
+            lag as f32 + (before - after) / (2.0 * curvature)
+        }
+    }
+}
@@ -0,0 +1,131 @@
+//! Output profiles bundle the conventions a particular kind of receiver expects, so switching
+//! between receivers is a single choice instead of reconfiguring every channel.
+
+use nannou_osc as osc;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutputProfile {
+    pub name: String,
+    /// The address template for each channel, see [`address()`][Self::address()].
+    pub address: String,
+    pub value_type: ValueType,
+    /// Normalized channel values are scaled to this range before they're encoded.
+    pub min: f32,
+    pub max: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ValueType {
+    Float,
+    /// Rounded to the nearest integer.
+    Int,
+}
+
+impl Default for OutputProfile {
+    fn default() -> Self {
+        Self {
+            name: String::from("Raw OSC"),
+            address: String::from("/{index}"),
+            value_type: ValueType::Float,
+            min: 0.0,
+            max: 1.0,
+        }
+    }
+}
+
+impl OutputProfile {
+    /// The profiles every instance starts out with.
+    pub fn built_in() -> Vec<Self> {
+        vec![
+            Self::default(),
+            Self {
+                name: String::from("DMX"),
+                address: String::from("/dmx/{index}"),
+                value_type: ValueType::Int,
+                min: 0.0,
+                max: 255.0,
+            },
+            Self {
+                name: String::from("Percent"),
+                min: 0.0,
+                max: 100.0,
+                ..Self::default()
+            },
+            Self {
+                name: String::from("Grouped"),
+                address: String::from("/{group}/{member}"),
+                ..Self::default()
+            },
+        ]
+    }
+
+    /// Fill in the address template's placeholders:
+    ///
+    /// - `{index}` is replaced by the channel's index, or by its name if names are used in
+    ///   addresses.
+    /// - `{name}` is replaced by the channel's name, or by its index if it doesn't have one.
+    /// - `{group}` is replaced by the channel's group. The slashes around it are merged for
+    ///   channels that aren't in a group.
+    /// - `{member}` is replaced by the channel's position within its group, counting from zero.
+    pub fn address(&self, index: &str, name: &str, group: &str, member: &str) -> String {
+        fill_template(&self.address, index, name, group, member)
+    }
+
+    /// Whether the address template needs the channel's position within its group, which takes
+    /// a look at all other channels to work out.
+    pub fn uses_member(&self) -> bool {
+        self.address.contains("{member}")
+    }
+
+    /// Scale a normalized `[0, 1]` value to this profile's range.
+    pub fn scale(&self, value: f32) -> f32 {
+        self.min + (self.max - self.min) * value
+    }
+}
+
+/// See [`OutputProfile::address()`].
+pub fn fill_template(template: &str, index: &str, name: &str, group: &str, member: &str) -> String {
+    let mut address = template
+        .replace("{index}", index)
+        .replace("{name}", name)
+        .replace("{group}", group)
+        .replace("{member}", member);
+    while address.contains("//") {
+        address = address.replace("//", "/");
+    }
+    if address.len() > 1 && address.ends_with('/') {
+        address.pop();
+    }
+
+    address
+}
+
+/// Check an address template that was typed in.
+pub fn validate_template(template: &str) -> Result<(), String> {
+    if !template.starts_with('/') {
+        return Err(format!(
+            "The template {template:?} needs to start with a slash"
+        ));
+    }
+
+    let unknown = fill_template(template, "", "", "", "");
+    if unknown.contains(['{', '}']) {
+        return Err(format!(
+            "The template {template:?} contains a placeholder other than {{index}}, {{name}}, \
+             {{group}}, and {{member}}"
+        ));
+    }
+
+    Ok(())
+}
+
+impl ValueType {
+    pub fn encode(self, value: f32) -> osc::Type {
+        match self {
+            ValueType::Float => osc::Type::Float(value),
+            ValueType::Int => osc::Type::Int(value.round() as i32),
+        }
+    }
+}
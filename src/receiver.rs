@@ -0,0 +1,581 @@
+//! Listens for OSC messages so a controller like TouchOSC can drive the channels. A message sent to
+//! a channel's own address (including the namespace), or to an input address it learned, sets that
+//! channel using the same units the channel sends in. Learning binds the address of the next
+//! incoming message to the armed channel. The same socket also accepts sync messages from other
+//! instances, MIDI messages in the format the `midi` module forwards them in, which are sent to
+//! the plugin's MIDI output, and when enabled, `/config` messages and messages to the `/all`
+//! alias. A single value sent to `{namespace}/all` sets every channel in use, and a list of values
+//! sets the channels in order. With pass-through enabled, every other message is forwarded to the
+//! destinations unchanged. A message to `{namespace}/home` ramps every channel back to its home
+//! position, or just the channel given as its argument, counting from one.
+//!
+//! nih-plug only lets an editor's `GuiContext` set parameters. While an editor has attached its
+//! context to the [`AutomationTarget`], received values are written to the host's parameters as
+//! automation gestures so the DAW can record them. Otherwise they override the channel's output
+//! instead, until the parameter is moved again.
+
+use nannou_osc as osc;
+use nih_plug::prelude::*;
+use socket2::{Domain, Protocol, Socket, Type};
+use std::collections::HashMap;
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::mpsc::SyncSender;
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::allowlist::Allowlist;
+use crate::dirty::DirtyFlags;
+use crate::midi::MidiMessage;
+use crate::profile::OutputProfile;
+use crate::remote_config::{self, CONFIG_PREFIX};
+use crate::status::ConnectionStatus;
+use crate::sync::{SyncState, SYNC_PREFIX};
+use crate::ticker::Executor;
+use crate::traffic::{Direction, TrafficLog};
+use crate::{BackgroundTask, ChannelConfig, SpaceRadioParams, NUM_CHANNELS};
+
+/// How long a receive may block before the settings and the stop flag are checked again.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// Large enough for any UDP datagram.
+const MAX_PACKET_SIZE: usize = 65_536;
+/// An automation gesture ends once a channel hasn't received anything for this long, so a fader
+/// being moved on a controller shows up as a single gesture in the host.
+const GESTURE_TIMEOUT: Duration = Duration::from_millis(250);
+/// The address after the namespace that sets all channels at once.
+const ALL_ADDRESS: &str = "/all";
+/// The address after the namespace that sends channels home.
+const HOME_ADDRESS: &str = "/home";
+
+/// Normalized channel values received over OSC, waiting to be picked up by the audio thread.
+pub struct RemoteValues {
+    /// The values' bits, so they can be written without locking.
+    values: Vec<AtomicU32>,
+    dirty: DirtyFlags,
+    /// When each channel last received a value, in nanoseconds since `epoch`. Zero means never.
+    received_at: Vec<AtomicU64>,
+    epoch: Instant,
+}
+
+impl Default for RemoteValues {
+    fn default() -> Self {
+        Self {
+            values: (0..NUM_CHANNELS).map(|_| AtomicU32::new(0)).collect(),
+            dirty: DirtyFlags::new(NUM_CHANNELS),
+            received_at: (0..NUM_CHANNELS).map(|_| AtomicU64::new(0)).collect(),
+            epoch: Instant::now(),
+        }
+    }
+}
+
+impl RemoteValues {
+    pub fn set(&self, index: usize, value: f32) {
+        self.values[index].store(value.to_bits(), Ordering::Relaxed);
+        self.dirty.mark(index);
+        self.mark_received(index);
+    }
+
+    /// Record that a channel received a value, also when it was written as automation instead.
+    pub fn mark_received(&self, index: usize) {
+        let nanos = self.epoch.elapsed().as_nanos() as u64;
+        self.received_at[index].store(nanos.max(1), Ordering::Relaxed);
+    }
+
+    /// Whether the channel received a value within the last `duration`. Used to keep from echoing
+    /// received values back to their sender.
+    pub fn received_within(&self, index: usize, duration: Duration) -> bool {
+        match self.received_at[index].load(Ordering::Relaxed) {
+            0 => false,
+            nanos => self.epoch.elapsed() < Duration::from_nanos(nanos) + duration,
+        }
+    }
+
+    pub fn value(&self, index: usize) -> f32 {
+        f32::from_bits(self.values[index].load(Ordering::Relaxed))
+    }
+
+    /// Move the indices of the channels that received something into `indices`.
+    pub fn drain(&self, indices: &mut Vec<usize>) {
+        self.dirty.drain(|index| indices.push(index));
+    }
+}
+
+/// The context received values are written to the host's parameters through.
+#[derive(Default)]
+pub struct AutomationTarget {
+    context: RwLock<Option<Arc<dyn GuiContext>>>,
+}
+
+impl AutomationTarget {
+    /// Called by the editor when it opens.
+    pub fn attach(&self, context: Arc<dyn GuiContext>) {
+        *self.context.write().unwrap() = Some(context);
+    }
+
+    /// Called by the editor when it closes.
+    pub fn detach(&self) {
+        *self.context.write().unwrap() = None;
+    }
+
+    /// The editor's context, while it's open.
+    pub fn context(&self) -> Option<Arc<dyn GuiContext>> {
+        self.context.read().unwrap().clone()
+    }
+}
+
+/// The channels with an automation gesture in progress, and when they last received a value.
+struct Gestures {
+    context: Arc<dyn GuiContext>,
+    last_received: Vec<Option<Instant>>,
+}
+
+impl Gestures {
+    fn new(context: Arc<dyn GuiContext>) -> Self {
+        Self {
+            context,
+            last_received: vec![None; NUM_CHANNELS],
+        }
+    }
+
+    fn set(&mut self, params: &SpaceRadioParams, index: usize, value: f32) {
+        let setter = ParamSetter::new(&*self.context);
+        let param = &params.array_params[index].val;
+        if self.last_received[index].is_none() {
+            setter.begin_set_parameter(param);
+        }
+        setter.set_parameter_normalized(param, value);
+        self.last_received[index] = Some(Instant::now());
+    }
+
+    /// End the gestures that timed out, or all of them.
+    fn end(&mut self, params: &SpaceRadioParams, all: bool) {
+        let setter = ParamSetter::new(&*self.context);
+        for (index, last_received) in self.last_received.iter_mut().enumerate() {
+            if last_received.is_some_and(|time| all || time.elapsed() >= GESTURE_TIMEOUT) {
+                setter.end_set_parameter(&params.array_params[index].val);
+                *last_received = None;
+            }
+        }
+    }
+}
+
+/// What the receiver shares with the rest of the plugin.
+pub(crate) struct ReceiverContext {
+    pub params: Arc<SpaceRadioParams>,
+    pub remote_values: Arc<RemoteValues>,
+    pub automation: Arc<AutomationTarget>,
+    pub sync: Arc<SyncState>,
+    pub traffic: Arc<TrafficLog>,
+    pub status: Arc<ConnectionStatus>,
+    /// Received MIDI messages on their way to the plugin's MIDI output.
+    pub midi_output: SyncSender<MidiMessage>,
+    pub executor: Arc<Mutex<Option<Executor>>>,
+    /// The channels asked to fade home by a `/home` message.
+    pub home_requests: Arc<DirtyFlags>,
+}
+
+/// Stops and joins its thread when dropped.
+pub struct OscReceiver {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl OscReceiver {
+    /// The socket is bound while `receive_port` is nonzero, and rebound when it changes.
+    pub(crate) fn spawn(context: Arc<ReceiverContext>) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = {
+            let stop = Arc::clone(&stop);
+            thread::Builder::new()
+                .name(String::from("space-radio-receiver"))
+                .spawn(move || run(&context, &stop))
+                .ok()
+        };
+
+        Self { stop, handle }
+    }
+}
+
+impl Drop for OscReceiver {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn run(context: &ReceiverContext, stop: &AtomicBool) {
+    let params = &*context.params;
+    let status = &*context.status;
+    // The socket and the port and multicast group it was bound for, or the ones that failed to
+    // bind so they aren't retried until the settings change
+    let mut socket: Option<(UdpSocket, (u16, Option<Ipv4Addr>))> = None;
+    let mut failed_bind = None;
+    let mut buffer = vec![0; MAX_PACKET_SIZE];
+    let mut gestures: Option<Gestures> = None;
+    let mut allowlist = Allowlist::default();
+    let mut address_map = AddressMap::default();
+    let mut dropped_packets = 0u64;
+
+    while !stop.load(Ordering::Relaxed) {
+        // Gestures are tied to the context they were started with, so they're ended when the
+        // editor closes or automation writing gets switched off
+        let gui_context = context
+            .automation
+            .context
+            .read()
+            .unwrap()
+            .clone()
+            .filter(|_| *params.write_automation.read().unwrap());
+        let same_context = match (&gestures, &gui_context) {
+            (Some(gestures), Some(gui_context)) => Arc::ptr_eq(&gestures.context, gui_context),
+            (None, None) => true,
+            _ => false,
+        };
+        if !same_context {
+            if let Some(gestures) = &mut gestures {
+                gestures.end(params, true);
+            }
+            gestures = gui_context.map(Gestures::new);
+        }
+        if let Some(gestures) = &mut gestures {
+            gestures.end(params, false);
+        }
+
+        // The port is released while the plugin is deactivated
+        let port = if params.active.load(Ordering::Relaxed) {
+            *params.receive_port.read().unwrap()
+        } else {
+            0
+        };
+        let group = (port != 0)
+            .then(|| *params.receive_group.read().unwrap())
+            .flatten();
+        if socket.as_ref().map_or((0, None), |(_, bound)| *bound) != (port, group) {
+            socket = None;
+            if port == 0 {
+                status.set_receive_error(None);
+            } else if failed_bind != Some((port, group)) {
+                match bind(port, group) {
+                    Ok(new_socket) => {
+                        match group {
+                            Some(group) => nih_log!(
+                                "Listening for OSC messages on port {port} in the multicast \
+                                 group {group}"
+                            ),
+                            None => nih_log!("Listening for OSC messages on port {port}"),
+                        }
+                        status.set_receive_error(None);
+                        failed_bind = None;
+                        socket = Some((new_socket, (port, group)));
+                    }
+                    Err(err) => {
+                        nih_error!("Could not listen for OSC messages on port {port}: {err}");
+                        status.set_receive_error(Some(format!(
+                            "Could not listen for OSC messages on port {port}: {err}"
+                        )));
+                        failed_bind = Some((port, group));
+                    }
+                }
+            }
+        }
+
+        let Some((socket, _)) = &socket else {
+            thread::sleep(POLL_INTERVAL);
+            continue;
+        };
+
+        let allowlist_entries = params.receive_allowlist.read().unwrap();
+        if allowlist.entries() != allowlist_entries.as_slice() {
+            allowlist = Allowlist::parse(&allowlist_entries);
+        }
+        drop(allowlist_entries);
+
+        match socket.recv_from(&mut buffer) {
+            Ok((_, source)) if !allowlist.allows(source.ip()) => {
+                dropped_packets += 1;
+                if dropped_packets == 1 || dropped_packets % 100 == 0 {
+                    nih_warn!(
+                        "Dropped an OSC packet from {source}, which is not on the allowlist \
+                         ({dropped_packets} dropped so far)"
+                    );
+                }
+            }
+            Ok((len, source)) => match osc::rosc::decoder::decode(&buffer[..len]) {
+                Ok(packet) => {
+                    let gestures = gestures.as_mut();
+                    let unhandled =
+                        handle_packet(context, gestures, &mut address_map, packet, source);
+                    forward(context, unhandled);
+                }
+                Err(err) => nih_warn!("Could not decode an OSC packet from {source}: {err:?}"),
+            },
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) => {}
+            Err(err) => {
+                nih_warn!("Could not receive OSC messages: {err}");
+                thread::sleep(POLL_INTERVAL);
+            }
+        }
+    }
+
+    if let Some(gestures) = &mut gestures {
+        gestures.end(params, true);
+    }
+}
+
+/// Bind the receive socket, joining a multicast group if there is one. Other programs on the same
+/// machine may want to listen to the group on the same port, so the address can be reused then.
+fn bind(port: u16, group: Option<Ipv4Addr>) -> io::Result<UdpSocket> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    if group.is_some() {
+        socket.set_reuse_address(true)?;
+    }
+    socket.bind(&SocketAddr::from((Ipv4Addr::UNSPECIFIED, port)).into())?;
+    let socket = UdpSocket::from(socket);
+    if let Some(group) = group {
+        socket.join_multicast_v4(&group, &Ipv4Addr::UNSPECIFIED)?;
+    }
+    socket.set_read_timeout(Some(POLL_INTERVAL))?;
+
+    Ok(socket)
+}
+
+fn handle_packet(
+    context: &ReceiverContext,
+    mut gestures: Option<&mut Gestures>,
+    address_map: &mut AddressMap,
+    packet: osc::Packet,
+    source: SocketAddr,
+) -> Vec<osc::Message> {
+    let ReceiverContext {
+        params,
+        remote_values,
+        sync,
+        traffic,
+        midi_output,
+        home_requests,
+        ..
+    } = context;
+    let mut messages = flatten(packet);
+    traffic.record_messages(Direction::Received, &messages);
+
+    // This needs to happen before the channels get locked below, since configuration messages may
+    // change them
+    let accept_config = *params.accept_config.read().unwrap();
+    messages.retain(|message| match message.addr.strip_prefix(CONFIG_PREFIX) {
+        Some(command) if accept_config => {
+            match remote_config::apply(params, command, &message.args) {
+                Ok(()) => nih_log!("Applied {} {:?} from {source}", message.addr, message.args),
+                Err(err) => nih_warn!("Could not apply {} from {source}: {err}", message.addr),
+            }
+            false
+        }
+        _ => true,
+    });
+    if messages.is_empty() {
+        return messages;
+    }
+
+    if let Some(index) = params.learn_channel.lock().unwrap().take() {
+        let addr = &messages[0].addr;
+        if let Some(channel) = params.channels.write().unwrap().get_mut(index) {
+            nih_log!("Channel {} learned {addr} from {source}", index + 1);
+            channel.input_address = Some(addr.clone());
+        }
+        params.invalidate_addresses();
+    }
+
+    let channels = params.channels.read().unwrap();
+    let default_channel = ChannelConfig::default();
+    let namespace = params.osc_namespace.read().unwrap();
+    let namespace = namespace.trim_end_matches('/');
+    address_map.refresh(params, &channels, namespace);
+    let profile = Arc::clone(&address_map.profile);
+    let channel_count = params.channel_count();
+    let accept_all = *params.accept_all.read().unwrap();
+    let mut unhandled = Vec::new();
+
+    for message in messages {
+        if accept_all && message.addr.strip_prefix(namespace) == Some(ALL_ADDRESS) {
+            let values: Vec<(usize, f32)> = match message.args.as_slice() {
+                [value] => arg_to_f32(value)
+                    .map(|value| (0..channel_count).map(|index| (index, value)).collect())
+                    .unwrap_or_default(),
+                args => args
+                    .iter()
+                    .take(channel_count)
+                    .enumerate()
+                    .filter_map(|(index, value)| Some((index, arg_to_f32(value)?)))
+                    .collect(),
+            };
+            for (index, value) in values {
+                let channel = channels.get(index).unwrap_or(&default_channel);
+                let value = channel.normalize(value, &profile);
+                set_channel(params, remote_values, gestures.as_deref_mut(), index, value);
+            }
+            continue;
+        }
+        if message.addr.strip_prefix(namespace) == Some(HOME_ADDRESS) {
+            match message.args.first().map(arg_to_f32) {
+                None => (0..channel_count).for_each(|index| home_requests.mark(index)),
+                Some(Some(channel)) if (1.0..=channel_count as f32).contains(&channel) => {
+                    home_requests.mark(channel as usize - 1)
+                }
+                Some(_) => nih_warn!("{} from {source} names no channel in use", message.addr),
+            }
+            continue;
+        }
+
+        let update = match message.addr.strip_prefix(SYNC_PREFIX) {
+            // Sync messages carry normalized values
+            Some(suffix) => sync.receive(suffix, &message.args),
+            None => address_map
+                .find(&message.addr, channel_count)
+                .zip(message.args.first().and_then(arg_to_f32))
+                .map(|(index, value)| {
+                    let channel = channels.get(index).unwrap_or(&default_channel);
+                    (index, channel.normalize(value, &profile))
+                }),
+        };
+
+        match update {
+            Some((index, value)) => {
+                set_channel(params, remote_values, gestures.as_deref_mut(), index, value)
+            }
+            None => {
+                let midi = message
+                    .addr
+                    .strip_prefix(namespace)
+                    .and_then(|addr| MidiMessage::from_osc(addr, &message.args));
+                match midi {
+                    // This only fills up while the plugin isn't processing audio
+                    Some(midi) => {
+                        if midi_output.try_send(midi).is_err() {
+                            nih_trace!("Dropped {midi:?} from {source}, the MIDI queue is full");
+                        }
+                    }
+                    None => {
+                        nih_trace!("Unhandled OSC message to {} from {source}", message.addr);
+                        unhandled.push(message);
+                    }
+                }
+            }
+        }
+    }
+
+    unhandled
+}
+
+/// Send messages the plugin didn't handle itself on to the destinations when pass-through is
+/// enabled. This happens after the settings have been unlocked again since the task executor
+/// needs them too.
+fn forward(context: &ReceiverContext, messages: Vec<osc::Message>) {
+    if messages.is_empty() || !*context.params.pass_through.read().unwrap() {
+        return;
+    }
+
+    // The executor isn't there until the host asks for it
+    let Some(execute) = context.executor.lock().unwrap().clone() else {
+        return;
+    };
+    for message in messages {
+        execute(BackgroundTask::Forward(message));
+    }
+}
+
+/// Write a received normalized value to a channel, as automation while that's possible.
+fn set_channel(
+    params: &SpaceRadioParams,
+    remote_values: &RemoteValues,
+    gestures: Option<&mut Gestures>,
+    index: usize,
+    value: f32,
+) {
+    match gestures {
+        // The parameter's callback takes it from there
+        Some(gestures) => {
+            gestures.set(params, index, value);
+            remote_values.mark_received(index);
+        }
+        None => remote_values.set(index, value),
+    }
+}
+
+/// Which channel each incoming address sets, so a message doesn't need every channel's address to
+/// be built to find its channel. This is rebuilt whenever the addresses change.
+#[derive(Default)]
+struct AddressMap {
+    /// The address generation this was built for.
+    generation: Option<u64>,
+    profile: Arc<OutputProfile>,
+    /// Addresses the channels learned. These take precedence over the channels' own addresses.
+    learned: HashMap<String, usize>,
+    /// The channels' own addresses, including the namespace.
+    addresses: HashMap<String, usize>,
+}
+
+impl AddressMap {
+    fn refresh(&mut self, params: &SpaceRadioParams, channels: &[ChannelConfig], namespace: &str) {
+        let generation = params.address_generation();
+        if self.generation == Some(generation) {
+            return;
+        }
+
+        self.generation = Some(generation);
+        self.profile = Arc::new(params.active_profile());
+        self.learned.clear();
+        self.addresses.clear();
+        // The channel count isn't part of the addresses, so every channel is included and the
+        // ones that aren't in use are filtered out when looking them up. When channels share an
+        // address, the first one wins.
+        for index in 0..NUM_CHANNELS {
+            let input_address = channels
+                .get(index)
+                .and_then(|channel| channel.input_address.clone());
+            if let Some(input_address) = input_address {
+                self.learned.entry(input_address).or_insert(index);
+            }
+            let address = params.channel_address(index, channels, &self.profile);
+            self.addresses
+                .entry(format!("{namespace}{address}"))
+                .or_insert(index);
+        }
+    }
+
+    /// The channel in use an incoming address belongs to.
+    fn find(&self, addr: &str, channel_count: usize) -> Option<usize> {
+        let in_use = |index: &usize| *index < channel_count;
+        self.learned
+            .get(addr)
+            .copied()
+            .filter(in_use)
+            .or_else(|| self.addresses.get(addr).copied().filter(in_use))
+    }
+}
+
+/// All messages in a packet, including those in nested bundles.
+fn flatten(packet: osc::Packet) -> Vec<osc::Message> {
+    match packet {
+        osc::Packet::Message(message) => vec![message],
+        osc::Packet::Bundle(bundle) => bundle.content.into_iter().flat_map(flatten).collect(),
+    }
+}
+
+pub(crate) fn arg_to_f32(arg: &osc::Type) -> Option<f32> {
+    match *arg {
+        osc::Type::Float(value) => Some(value),
+        osc::Type::Double(value) => Some(value as f32),
+        osc::Type::Int(value) => Some(value as f32),
+        osc::Type::Long(value) => Some(value as f32),
+        osc::Type::Bool(value) => Some(if value { 1.0 } else { 0.0 }),
+        _ => None,
+    }
+    .filter(|value| value.is_finite())
+}
@@ -0,0 +1,164 @@
+//! Runtime configuration through `/config/...` OSC messages on the receive socket, so headless
+//! playback rigs can be reconfigured without opening the plugin's editor. Only the persisted
+//! settings can be changed this way, since parameters can only be set by the host or the editor.
+//!
+//! - `/config/destination <address> <port>` sends everything to a single UDP destination.
+//! - `/config/destination/add <address> <port>` adds a UDP destination.
+//! - `/config/destination/clear` removes all destinations.
+//! - `/config/namespace <namespace>`
+//! - `/config/profile <name>` switches to another output profile.
+//! - `/config/rate <messages per second>` caps every channel's message rate, zero lifts the cap.
+//! - `/config/keepalive <ms>` and `/config/resync <ms>` set those intervals.
+//! - `/config/tick <ms>` sends channel updates at a fixed interval from a separate thread, zero
+//!   sends them at the end of every block.
+//! - `/config/snapshot <program>` stores the channels' current values as the snapshot that MIDI
+//!   program changes to that program recall.
+//! - `/config/channels <count>` sets how many channels are in use.
+//! - `/config/learn <channel>` arms OSC and MIDI learn for a channel, counting from one.
+//! - `/config/route <channel> <address> [<destination>]` adds a route to a channel, see the
+//!   `routing` module. Without a destination the route sends to every destination.
+//! - `/config/route/clear <channel>` removes a channel's routes.
+
+use nannou_osc as osc;
+
+use crate::destination::Destination;
+use crate::routing::Route;
+use crate::snapshot::{self, Snapshot};
+use crate::{normalize_namespace, SpaceRadioParams, NUM_CHANNELS};
+
+/// The address prefix for configuration messages. These don't use the namespace.
+pub const CONFIG_PREFIX: &str = "/config/";
+
+/// Apply a configuration message. `command` is the part of the address after [`CONFIG_PREFIX`].
+pub(crate) fn apply(
+    params: &SpaceRadioParams,
+    command: &str,
+    args: &[osc::Type],
+) -> Result<(), String> {
+    match (command, args) {
+        ("destination", [address, port]) => {
+            *params.destinations.write().unwrap() = vec![destination(address, port)?];
+        }
+        ("destination/add", [address, port]) => {
+            params
+                .destinations
+                .write()
+                .unwrap()
+                .push(destination(address, port)?);
+        }
+        ("destination/clear", []) => params.destinations.write().unwrap().clear(),
+        ("namespace", [osc::Type::String(namespace)]) => {
+            *params.osc_namespace.write().unwrap() = normalize_namespace(namespace);
+            params.invalidate_addresses();
+        }
+        ("profile", [osc::Type::String(name)]) => {
+            let profiles = params.profiles.read().unwrap();
+            if !profiles.iter().any(|profile| &profile.name == name) {
+                return Err(format!("There's no profile called {name:?}"));
+            }
+            *params.active_profile.write().unwrap() = name.clone();
+            params.invalidate_addresses();
+        }
+        ("rate", [rate]) => {
+            let rate = number(rate)?.max(0.0);
+            for channel in params.channels.write().unwrap().iter_mut() {
+                channel.max_rate = rate;
+            }
+        }
+        ("keepalive", [interval]) => {
+            *params.keepalive_interval_ms.write().unwrap() = number(interval)?.max(0.0) as u32;
+        }
+        ("resync", [interval]) => {
+            *params.resync_interval_ms.write().unwrap() = number(interval)?.max(0.0) as u32;
+        }
+        ("tick", [interval]) => {
+            *params.send_tick_ms.write().unwrap() = number(interval)?.max(0.0) as u32;
+        }
+        ("snapshot", [program]) => {
+            let program = number(program)?;
+            if !(0.0..128.0).contains(&program) {
+                return Err(format!("{program} is not a valid MIDI program"));
+            }
+            let values = params.array_params.iter().map(|param| param.val.value());
+            let snapshot = Snapshot::new(program as u8, values);
+            snapshot::store(&mut params.snapshots.write().unwrap(), snapshot);
+        }
+        ("channels", [count]) => {
+            let count = number(count)?;
+            if !(1.0..=NUM_CHANNELS as f32).contains(&count) {
+                return Err(format!(
+                    "The channel count needs to be between 1 and {NUM_CHANNELS}"
+                ));
+            }
+            *params.channel_count.write().unwrap() = count as u32;
+        }
+        ("learn", [channel]) => {
+            *params.learn_channel.lock().unwrap() = Some(channel_index(params, channel)?);
+        }
+        ("route", [channel, osc::Type::String(address), rest @ ..]) if rest.len() <= 1 => {
+            let index = channel_index(params, channel)?;
+            if !address.is_empty() && !address.starts_with('/') {
+                return Err(format!(
+                    "The address {address:?} needs to start with a slash"
+                ));
+            }
+            let destination = match rest {
+                [osc::Type::String(destination)] => destination.clone(),
+                [destination] => {
+                    return Err(format!("Expected a destination, got {destination:?}"))
+                }
+                _ => String::new(),
+            };
+            if let Some(channel) = params.channels.write().unwrap().get_mut(index) {
+                channel.routes.push(Route {
+                    address: address.clone(),
+                    destination,
+                });
+            }
+        }
+        ("route/clear", [channel]) => {
+            let index = channel_index(params, channel)?;
+            if let Some(channel) = params.channels.write().unwrap().get_mut(index) {
+                channel.routes.clear();
+            }
+        }
+        _ => return Err(format!("Unknown configuration message {command} {args:?}")),
+    }
+
+    Ok(())
+}
+
+fn destination(address: &osc::Type, port: &osc::Type) -> Result<Destination, String> {
+    let address = match address {
+        osc::Type::String(address) => address.clone(),
+        address => return Err(format!("Expected an address, got {address:?}")),
+    };
+    let port = number(port)?;
+    if !(1.0..=u16::MAX as f32).contains(&port) {
+        return Err(format!("{port} is not a valid port"));
+    }
+
+    Ok(Destination {
+        address,
+        port: port as u16,
+        ..Destination::default()
+    })
+}
+
+/// Parse a channel number counting from one, returning the channel's index.
+fn channel_index(params: &SpaceRadioParams, channel: &osc::Type) -> Result<usize, String> {
+    let channel = number(channel)? as usize;
+    if !(1..=params.channel_count()).contains(&channel) {
+        return Err(format!("There's no channel {channel}"));
+    }
+
+    Ok(channel - 1)
+}
+
+fn number(arg: &osc::Type) -> Result<f32, String> {
+    match *arg {
+        osc::Type::Float(value) if value.is_finite() => Ok(value),
+        osc::Type::Int(value) => Ok(value as f32),
+        ref arg => Err(format!("Expected a number, got {arg:?}")),
+    }
+}
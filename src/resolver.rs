@@ -0,0 +1,151 @@
+//! Resolves destination hostnames like `visuals-laptop.local` off the task executor. DNS lookups
+//! can take seconds, and the addresses behind a hostname change whenever DHCP hands out new leases,
+//! so names are looked up on their own thread and looked up again every once in a while. Sending
+//! only ever consults the cached results.
+
+use nih_plug::prelude::*;
+use std::collections::HashMap;
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::SpaceRadioParams;
+
+/// How often the resolver checks for hostnames it hasn't looked up yet.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// How often hostnames that have already been resolved are looked up again.
+const RERESOLVE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The most recently resolved address for every `host:port` target.
+#[derive(Default)]
+pub struct ResolvedAddresses {
+    addresses: RwLock<HashMap<String, Resolved>>,
+}
+
+struct Resolved {
+    /// `None` if the target has never resolved successfully.
+    addr: Option<SocketAddr>,
+    resolved_at: Instant,
+}
+
+impl ResolvedAddresses {
+    /// The address to send to for a target. IP literals are used as is, hostnames are `None` until
+    /// the resolver has gotten to them.
+    pub fn lookup(&self, target: &str) -> Option<SocketAddr> {
+        if let Ok(addr) = target.parse() {
+            return Some(addr);
+        }
+
+        self.addresses
+            .read()
+            .unwrap()
+            .get(target)
+            .and_then(|resolved| resolved.addr)
+    }
+}
+
+/// Stops and joins its thread when dropped.
+pub struct Resolver {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Resolver {
+    pub(crate) fn spawn(params: Arc<SpaceRadioParams>, addresses: Arc<ResolvedAddresses>) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = {
+            let stop = Arc::clone(&stop);
+            thread::Builder::new()
+                .name(String::from("space-radio-resolver"))
+                .spawn(move || run(&params, &addresses, &stop))
+                .ok()
+        };
+
+        Self { stop, handle }
+    }
+}
+
+impl Drop for Resolver {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn run(params: &SpaceRadioParams, addresses: &ResolvedAddresses, stop: &AtomicBool) {
+    while !stop.load(Ordering::Relaxed) {
+        let destinations = params.destinations.read().unwrap();
+        let sync_peers = params.sync_peers.read().unwrap();
+        let targets: Vec<String> = destinations
+            .iter()
+            .chain(sync_peers.iter())
+            .filter(|destination| destination.enabled)
+            .map(|destination| destination.target())
+            .filter(|target| target.parse::<SocketAddr>().is_err())
+            .collect();
+        drop(destinations);
+        drop(sync_peers);
+
+        let due: Vec<String> = {
+            let addresses = addresses.addresses.read().unwrap();
+            targets
+                .iter()
+                .filter(|target| match addresses.get(target.as_str()) {
+                    Some(resolved) => resolved.resolved_at.elapsed() >= RERESOLVE_INTERVAL,
+                    None => true,
+                })
+                .cloned()
+                .collect()
+        };
+
+        // The lock isn't held during lookups so sending never waits on DNS
+        for target in due {
+            let result = resolve(&target);
+            let mut addresses = addresses.addresses.write().unwrap();
+            let previous = addresses.get(&target).and_then(|resolved| resolved.addr);
+            let addr = match result {
+                Ok(addr) => {
+                    if previous != Some(addr) {
+                        nih_log!("Resolved {target} to {addr}");
+                    }
+                    Some(addr)
+                }
+                // A hostname that briefly fails to resolve keeps its last known address
+                Err(err) => {
+                    nih_warn!("Could not resolve {target}: {err}");
+                    previous
+                }
+            };
+
+            addresses.insert(
+                target,
+                Resolved {
+                    addr,
+                    resolved_at: Instant::now(),
+                },
+            );
+        }
+
+        addresses
+            .addresses
+            .write()
+            .unwrap()
+            .retain(|target, _| targets.contains(target));
+
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+fn resolve(target: &str) -> io::Result<SocketAddr> {
+    target.to_socket_addrs()?.next().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::AddrNotAvailable,
+            format!("{target} did not resolve to any addresses"),
+        )
+    })
+}
@@ -0,0 +1,31 @@
+//! Routes send a channel to specific addresses at specific destinations, instead of sending the
+//! channel's own address to every destination. A channel can have any number of routes, so a
+//! single fader can drive a parameter on the lighting desk and a different one on the visuals
+//! machine without an OSC router in between.
+
+use serde::{Deserialize, Serialize};
+
+use crate::destination::Destination;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Route {
+    /// The OSC address to send to, without the namespace. Empty uses the channel's own address.
+    pub address: String,
+    /// The `address:port` of one of the destinations, as in [`Destination::target()`]. Empty sends
+    /// to every destination.
+    pub destination: String,
+}
+
+/// The destinations messages for `target` are sent to. An empty target means all of them, and a
+/// target that isn't in the destination list doesn't receive anything.
+pub fn select<'a>(destinations: &'a [Destination], target: &str) -> &'a [Destination] {
+    if target.is_empty() {
+        return destinations;
+    }
+
+    destinations
+        .iter()
+        .find(|destination| destination.target() == target)
+        .map_or(&[], std::slice::from_ref)
+}
@@ -0,0 +1,112 @@
+//! Ramps for the channels with smoothing enabled. Smoothed channels send their trajectory at a
+//! fixed rate instead of on every block, and always send the value they end up at.
+
+use nih_plug::prelude::*;
+
+#[derive(Default)]
+pub struct ChannelSmoothers {
+    smoothers: Vec<Smoother<f32>>,
+    /// The smoothing time each smoother was created with, to tell when a channel's setting changes.
+    times: Vec<f32>,
+    /// Samples since the smoothed channels last sent their intermediate values.
+    elapsed: f32,
+}
+
+impl ChannelSmoothers {
+    pub fn new(num_channels: usize) -> Self {
+        Self {
+            smoothers: (0..num_channels).map(|_| Smoother::none()).collect(),
+            times: vec![0.0; num_channels],
+            elapsed: 0.0,
+        }
+    }
+
+    /// Ramp a channel to `target` over `smoothing_ms`. A channel that isn't already ramping starts
+    /// from `current`, one that is continues from where it's at.
+    pub fn start(
+        &mut self,
+        index: usize,
+        smoothing_ms: f32,
+        sample_rate: f32,
+        current: f32,
+        target: f32,
+    ) {
+        if self.times[index] != smoothing_ms {
+            self.smoothers[index] = Smoother::new(SmoothingStyle::Linear(smoothing_ms));
+            self.times[index] = smoothing_ms;
+        }
+
+        let smoother = &mut self.smoothers[index];
+        if !smoother.is_smoothing() {
+            smoother.reset(current);
+        }
+        smoother.set_target(sample_rate, target);
+    }
+
+    /// Stop a channel's ramp at `value`.
+    pub fn reset(&mut self, index: usize, value: f32) {
+        self.smoothers[index].reset(value);
+    }
+
+    /// Advance every ramping channel by a block, calling `f` with the channel's new value and
+    /// whether it should be sent. That's the case once every `emit_interval` samples, and when the
+    /// ramp ends.
+    pub fn advance(
+        &mut self,
+        samples: u32,
+        emit_interval: f32,
+        mut f: impl FnMut(usize, f32, bool),
+    ) {
+        self.elapsed += samples as f32;
+        let emit = self.elapsed >= emit_interval;
+        if emit {
+            self.elapsed %= emit_interval;
+        }
+        for (index, smoother) in self.smoothers.iter_mut().enumerate() {
+            if !smoother.is_smoothing() {
+                continue;
+            }
+
+            let value = smoother.next_step(samples);
+            f(index, value, emit || !smoother.is_smoothing());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_step_sends_a_ramp() {
+        let mut smoothers = ChannelSmoothers::new(2);
+        smoothers.start(0, 100.0, 1000.0, 0.0, 1.0);
+
+        // 10 sample blocks at 1 kHz, sending every 20 samples over a 100 ms ramp
+        let mut sent = Vec::new();
+        for _ in 0..20 {
+            smoothers.advance(10, 20.0, |index, value, send| {
+                assert_eq!(index, 0);
+                if send {
+                    sent.push(value);
+                }
+            });
+        }
+
+        assert!(sent.len() >= 4, "only sent {sent:?}");
+        assert!(sent.windows(2).all(|pair| pair[0] < pair[1]), "{sent:?}");
+        assert!(sent[0] > 0.0);
+        assert_eq!(sent.last(), Some(&1.0));
+    }
+
+    #[test]
+    fn reset_stops_the_ramp() {
+        let mut smoothers = ChannelSmoothers::new(1);
+        smoothers.start(0, 100.0, 1000.0, 0.0, 1.0);
+        smoothers.reset(0, 0.5);
+
+        let mut called = false;
+        smoothers.advance(10, 20.0, |_, _, _| called = true);
+        assert!(!called);
+    }
+}
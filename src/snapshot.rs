@@ -0,0 +1,29 @@
+//! Stored sets of channel values that can be recalled all at once, e.g. from a foot controller
+//! sending MIDI program changes between scenes.
+
+use serde::{Deserialize, Serialize};
+
+use crate::NUM_CHANNELS;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// The MIDI program that recalls this snapshot.
+    pub program: u8,
+    /// Every channel's normalized value.
+    pub values: Vec<f32>,
+}
+
+impl Snapshot {
+    pub fn new(program: u8, values: impl IntoIterator<Item = f32>) -> Self {
+        let mut values: Vec<f32> = values.into_iter().take(NUM_CHANNELS).collect();
+        values.resize(NUM_CHANNELS, 0.0);
+
+        Self { program, values }
+    }
+}
+
+/// Store a snapshot for a program, replacing the one that was stored for it before.
+pub fn store(snapshots: &mut Vec<Snapshot>, snapshot: Snapshot) {
+    snapshots.retain(|stored| stored.program != snapshot.program);
+    snapshots.push(snapshot);
+}
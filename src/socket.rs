@@ -0,0 +1,276 @@
+//! The socket OSC packets are sent through. UDP is the default, and it can also be used to reach
+//! a whole subnet through a broadcast address or a multicast group. Some receivers only accept OSC
+//! over TCP. Since TCP is a stream, packets then need to be framed, either with SLIP as specified
+//! by OSC 1.1 or with the 32-bit size prefix from OSC 1.0.
+
+use nannou_osc as osc;
+use nih_plug::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream, UdpSocket};
+use std::time::{Duration, Instant};
+
+use crate::destination::Destination;
+
+/// How long to wait before trying to connect again after a TCP connection failed.
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(500);
+const WRITE_TIMEOUT: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Protocol {
+    #[default]
+    Udp,
+    /// UDP to a subnet broadcast address like `192.168.1.255`.
+    UdpBroadcast,
+    /// UDP to a multicast group like `239.255.0.1`. Sending to a group doesn't require joining it,
+    /// so there's no membership to manage here.
+    UdpMulticast,
+    Tcp,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Framing {
+    /// Double-ended SLIP, as used by OSC 1.1.
+    #[default]
+    Slip,
+    /// A big-endian 32-bit size followed by the packet, as used by OSC 1.0.
+    LengthPrefixed,
+}
+
+/// Which local interface and port UDP packets are sent from. Venue firewalls often only let
+/// traffic from a fixed source port through, and machines with multiple network interfaces may
+/// need to pick one. TCP connections always use an OS-assigned port.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LocalBind {
+    /// `None` binds to all interfaces. The fixed port only applies to this address's family, the
+    /// socket for the other family always uses an OS-assigned port.
+    pub address: Option<IpAddr>,
+    /// Zero lets the OS pick a port.
+    pub port: u16,
+}
+
+pub struct OscSocket {
+    /// What the sockets were bound with.
+    local_bind: LocalBind,
+    udp_v4: UdpSocket,
+    /// `None` if the system doesn't support IPv6. Using a separate socket per address family
+    /// avoids depending on the platform's default for dual-stack sockets.
+    udp_v6: Option<UdpSocket>,
+    /// The TTL currently set for outgoing IPv4 multicast packets.
+    multicast_ttl: u32,
+    /// TCP connections by `address:port` target.
+    tcp: HashMap<String, TcpConnection>,
+}
+
+#[derive(Default)]
+struct TcpConnection {
+    stream: Option<TcpStream>,
+    /// The address `stream` is connected to. A hostname that resolves to a new address gets a new
+    /// connection.
+    addr: Option<SocketAddr>,
+    /// Connection attempts are skipped until this point after a failure.
+    next_connect_attempt: Option<Instant>,
+}
+
+/// Encode a packet once so it can be sent to multiple destinations.
+pub fn encode(packet: &osc::Packet) -> io::Result<Vec<u8>> {
+    osc::rosc::encoder::encode(packet)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, format!("{err:?}")))
+}
+
+impl OscSocket {
+    /// Bind the UDP sockets. TCP connections are made on demand.
+    pub fn bind(local_bind: &LocalBind) -> io::Result<Self> {
+        let (v4_addr, v6_addr) = match local_bind.address {
+            None => (
+                SocketAddr::from((Ipv4Addr::UNSPECIFIED, local_bind.port)),
+                SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0)),
+            ),
+            Some(IpAddr::V4(ip)) => (
+                SocketAddr::from((ip, local_bind.port)),
+                SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0)),
+            ),
+            Some(IpAddr::V6(ip)) => (
+                SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)),
+                SocketAddr::from((ip, local_bind.port)),
+            ),
+        };
+
+        // A send that blocks on a full OS buffer shouldn't hold up the background tasks for good
+        let udp_v4 = UdpSocket::bind(v4_addr)?;
+        udp_v4.set_write_timeout(Some(WRITE_TIMEOUT))?;
+        udp_v4.set_broadcast(true)?;
+        // Local receivers should be able to listen in on multicast groups too
+        udp_v4.set_multicast_loop_v4(true)?;
+
+        let udp_v6 = match UdpSocket::bind(v6_addr).and_then(|udp_v6| {
+            udp_v6.set_write_timeout(Some(WRITE_TIMEOUT))?;
+            udp_v6.set_multicast_loop_v6(true)?;
+            Ok(udp_v6)
+        }) {
+            Ok(udp_v6) => Some(udp_v6),
+            // Explicitly asking for an IPv6 interface shouldn't silently fall back to IPv4
+            Err(err) if matches!(local_bind.address, Some(IpAddr::V6(_))) => return Err(err),
+            Err(err) => {
+                nih_log!("IPv6 is not available, only sending over IPv4: {err}");
+                None
+            }
+        };
+
+        Ok(Self {
+            local_bind: local_bind.clone(),
+            multicast_ttl: udp_v4.multicast_ttl_v4()?,
+            udp_v4,
+            udp_v6,
+            tcp: HashMap::new(),
+        })
+    }
+
+    pub fn local_bind(&self) -> &LocalBind {
+        &self.local_bind
+    }
+
+    /// The port the IPv4 socket ended up bound to.
+    pub fn local_port(&self) -> Option<u16> {
+        self.udp_v4.local_addr().ok().map(|addr| addr.port())
+    }
+
+    /// Send an encoded packet to a destination. `addr` is the destination's resolved address.
+    pub fn send(
+        &mut self,
+        bytes: &[u8],
+        destination: &Destination,
+        addr: SocketAddr,
+    ) -> io::Result<()> {
+        let target = destination.target();
+        match destination.protocol {
+            Protocol::Udp | Protocol::UdpBroadcast | Protocol::UdpMulticast => {
+                let udp = match addr {
+                    SocketAddr::V4(_) => &self.udp_v4,
+                    SocketAddr::V6(_) => self.udp_v6.as_ref().ok_or_else(|| {
+                        io::Error::new(
+                            io::ErrorKind::AddrNotAvailable,
+                            format!("Cannot send to {target}, IPv6 is not available"),
+                        )
+                    })?,
+                };
+
+                if destination.protocol == Protocol::UdpMulticast
+                    && addr.is_ipv4()
+                    && self.multicast_ttl != destination.multicast_ttl
+                {
+                    udp.set_multicast_ttl_v4(destination.multicast_ttl)?;
+                    self.multicast_ttl = destination.multicast_ttl;
+                }
+
+                udp.send_to(bytes, addr).map(|_| ())
+            }
+            Protocol::Tcp => {
+                let connection = self.tcp.entry(target.clone()).or_default();
+                let stream = connection.connect(&target, addr)?;
+                let result = match destination.framing {
+                    Framing::Slip => stream.write_all(&slip_encode(bytes)),
+                    Framing::LengthPrefixed => stream
+                        .write_all(&(bytes.len() as u32).to_be_bytes())
+                        .and_then(|_| stream.write_all(bytes)),
+                };
+
+                // The connection is reestablished during the next send
+                if result.is_err() {
+                    connection.stream = None;
+                    connection.next_connect_attempt = Some(Instant::now() + RECONNECT_INTERVAL);
+                }
+
+                result
+            }
+        }
+    }
+
+    /// Close TCP connections to targets that are no longer in use.
+    pub fn retain_tcp_targets(&mut self, mut in_use: impl FnMut(&str) -> bool) {
+        self.tcp.retain(|target, _| in_use(target));
+    }
+}
+
+impl fmt::Display for LocalBind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let address = match self.address {
+            Some(IpAddr::V6(ip)) => format!("[{ip}]"),
+            Some(IpAddr::V4(ip)) => ip.to_string(),
+            None => String::from("*"),
+        };
+        match self.port {
+            0 => write!(f, "{address}:*"),
+            port => write!(f, "{address}:{port}"),
+        }
+    }
+}
+
+impl TcpConnection {
+    /// Get the stream, connecting to `target` at `addr` if there's no connection to that address
+    /// yet.
+    fn connect(&mut self, target: &str, addr: SocketAddr) -> io::Result<&mut TcpStream> {
+        if self.addr != Some(addr) {
+            self.addr = Some(addr);
+            self.stream = None;
+            self.next_connect_attempt = None;
+        }
+
+        if self.stream.is_none() {
+            if let Some(next_connect_attempt) = self.next_connect_attempt {
+                if Instant::now() < next_connect_attempt {
+                    return Err(io::Error::new(
+                        io::ErrorKind::NotConnected,
+                        format!("Waiting to reconnect to {target}"),
+                    ));
+                }
+            }
+
+            match connect_tcp(addr) {
+                Ok(stream) => {
+                    nih_log!("Connected to {target} ({addr}) over TCP");
+                    self.next_connect_attempt = None;
+                    self.stream = Some(stream);
+                }
+                Err(err) => {
+                    self.next_connect_attempt = Some(Instant::now() + RECONNECT_INTERVAL);
+                    return Err(err);
+                }
+            }
+        }
+
+        Ok(self.stream.as_mut().unwrap())
+    }
+}
+
+fn connect_tcp(addr: SocketAddr) -> io::Result<TcpStream> {
+    let stream = TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT)?;
+    stream.set_nodelay(true)?;
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+
+    Ok(stream)
+}
+
+const SLIP_END: u8 = 0xc0;
+const SLIP_ESC: u8 = 0xdb;
+const SLIP_ESC_END: u8 = 0xdc;
+const SLIP_ESC_ESC: u8 = 0xdd;
+
+fn slip_encode(bytes: &[u8]) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(bytes.len() + 2);
+    encoded.push(SLIP_END);
+    for &byte in bytes {
+        match byte {
+            SLIP_END => encoded.extend_from_slice(&[SLIP_ESC, SLIP_ESC_END]),
+            SLIP_ESC => encoded.extend_from_slice(&[SLIP_ESC, SLIP_ESC_ESC]),
+            byte => encoded.push(byte),
+        }
+    }
+    encoded.push(SLIP_END);
+
+    encoded
+}
@@ -0,0 +1,107 @@
+//! Tracks what's currently going wrong with the plugin's sockets, so problems show up in the
+//! editor instead of only in the log. Errors are cleared as soon as the thing they're about works
+//! again. Destinations that keep failing are backed off from, so an unreachable destination doesn't
+//! cost a failed send for every message.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long sending to a destination is paused after it first fails. This doubles with every
+/// failure after that, up to `MAX_BACKOFF`.
+const MIN_BACKOFF: Duration = Duration::from_millis(50);
+const MAX_BACKOFF: Duration = Duration::from_secs(2);
+
+#[derive(Default)]
+pub struct ConnectionStatus {
+    /// Why the sending socket couldn't be bound to the configured local address.
+    bind_error: Mutex<Option<String>>,
+    /// Why the receiving socket couldn't be bound.
+    receive_error: Mutex<Option<String>>,
+    /// Every destination that's currently failing, by `address:port` target.
+    send_errors: Mutex<BTreeMap<String, SendFailure>>,
+}
+
+struct SendFailure {
+    last_error: String,
+    /// The number of failed sends since the destination last worked.
+    count: u64,
+    /// Nothing is sent to the destination until then.
+    retry_at: Instant,
+}
+
+impl ConnectionStatus {
+    pub fn set_bind_error(&self, error: Option<String>) {
+        *self.bind_error.lock().unwrap() = error;
+    }
+
+    pub fn set_receive_error(&self, error: Option<String>) {
+        *self.receive_error.lock().unwrap() = error;
+    }
+
+    /// Whether to try sending to a target, which is false while backing off from it.
+    pub fn should_send(&self, target: &str) -> bool {
+        self.send_errors
+            .lock()
+            .unwrap()
+            .get(target)
+            .map_or(true, |failure| Instant::now() >= failure.retry_at)
+    }
+
+    /// Record the outcome of sending to a target. Returns the number of failures in a row, which
+    /// is zero after a successful send.
+    pub fn send_result(&self, target: &str, result: Result<(), String>) -> u64 {
+        let mut send_errors = self.send_errors.lock().unwrap();
+        match result {
+            Ok(()) => {
+                // Avoids allocating the key on every successful send
+                if send_errors.contains_key(target) {
+                    send_errors.remove(target);
+                }
+                0
+            }
+            Err(err) => {
+                let failure = send_errors
+                    .entry(target.to_owned())
+                    .or_insert_with(|| SendFailure {
+                        last_error: String::new(),
+                        count: 0,
+                        retry_at: Instant::now(),
+                    });
+                let backoff = MIN_BACKOFF
+                    .saturating_mul(1 << failure.count.min(16))
+                    .min(MAX_BACKOFF);
+                failure.last_error = err;
+                failure.count += 1;
+                failure.retry_at = Instant::now() + backoff;
+                failure.count
+            }
+        }
+    }
+
+    /// Forget about targets that are no longer being sent to.
+    pub fn retain_targets(&self, mut keep: impl FnMut(&str) -> bool) {
+        self.send_errors
+            .lock()
+            .unwrap()
+            .retain(|target, _| keep(target));
+    }
+
+    /// Everything that's currently wrong. Empty when everything works.
+    pub fn errors(&self) -> Vec<String> {
+        let bind_error = self.bind_error.lock().unwrap().clone();
+        let receive_error = self.receive_error.lock().unwrap().clone();
+        let send_errors = self.send_errors.lock().unwrap();
+
+        bind_error
+            .into_iter()
+            .chain(receive_error)
+            .chain(send_errors.iter().map(|(target, failure)| {
+                format!(
+                    "Could not send to {target}: {} ({} failed sends)",
+                    failure.last_error, failure.count
+                )
+            }))
+            .collect()
+    }
+}
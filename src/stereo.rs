@@ -0,0 +1,99 @@
+//! Measures the stereo image of the plugin's audio input, for panning visual elements along with
+//! the sound. Only the first two input channels are considered, and mono input sends nothing.
+//!
+//! - `/stereo/correlation <correlation>` between -1 for out of phase channels, zero for unrelated
+//!   channels, and 1 for mono.
+//! - `/stereo/width <width>`, the side signal's share of the energy. Zero for mono, 0.5 for
+//!   unrelated channels, and 1 for out of phase channels.
+//! - `/stereo/balance <balance>` between -1 when only the left channel is playing and 1 when only
+//!   the right channel is.
+//!
+//! All of them are measured over the time since the previous message, and silence counts as mono.
+
+use nannou_osc as osc;
+use nih_plug::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StereoImage {
+    pub correlation: f32,
+    pub width: f32,
+    pub balance: f32,
+}
+
+impl StereoImage {
+    pub fn to_osc(self) -> [(&'static str, Vec<osc::Type>); 3] {
+        [
+            (
+                "/stereo/correlation",
+                vec![osc::Type::Float(self.correlation)],
+            ),
+            ("/stereo/width", vec![osc::Type::Float(self.width)]),
+            ("/stereo/balance", vec![osc::Type::Float(self.balance)]),
+        ]
+    }
+}
+
+#[derive(Default)]
+pub struct StereoMeter {
+    sum_left_right: f64,
+    sum_left_squares: f64,
+    sum_right_squares: f64,
+    /// Samples measured since the stereo image was last sent.
+    num_samples: usize,
+}
+
+impl StereoMeter {
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Measure the next block of input. Returns the stereo image once `interval_ms` has passed
+    /// since it was last returned, or after every block if that's zero.
+    pub fn process(
+        &mut self,
+        buffer: &Buffer,
+        interval_ms: u32,
+        sample_rate: f32,
+    ) -> Option<StereoImage> {
+        let [left, right, ..] = buffer.as_slice_immutable() else {
+            return None;
+        };
+        for (&left, &right) in left.iter().zip(right.iter()) {
+            let (left, right) = (left as f64, right as f64);
+            self.sum_left_right += left * right;
+            self.sum_left_squares += left * left;
+            self.sum_right_squares += right * right;
+        }
+        self.num_samples += buffer.samples();
+
+        let interval_samples = (interval_ms as f32 / 1000.0 * sample_rate) as usize;
+        if self.num_samples == 0 || self.num_samples < interval_samples {
+            return None;
+        }
+
+        // The mid and side energies follow from the sums, with mid and side as the channels'
+        // average and half their difference
+        let energy = self.sum_left_squares + self.sum_right_squares;
+        let mid = (energy + 2.0 * self.sum_left_right) / 4.0;
+        let side = (energy - 2.0 * self.sum_left_right) / 4.0;
+        let image = if energy > f64::EPSILON {
+            let (left_rms, right_rms) =
+                (self.sum_left_squares.sqrt(), self.sum_right_squares.sqrt());
+            StereoImage {
+                correlation: (self.sum_left_right / (left_rms * right_rms).max(f64::EPSILON))
+                    .clamp(-1.0, 1.0) as f32,
+                width: (side / (mid + side)).clamp(0.0, 1.0) as f32,
+                balance: ((right_rms - left_rms) / (right_rms + left_rms)) as f32,
+            }
+        } else {
+            StereoImage {
+                correlation: 1.0,
+                width: 0.0,
+                balance: 0.0,
+            }
+        };
+        self.reset();
+
+        Some(image)
+    }
+}
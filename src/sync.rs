@@ -0,0 +1,105 @@
+//! Keeps the channels of several Space Radio instances mirrored, e.g. on two machines running the
+//! same set. Every local change is sent to the configured sync peers as
+//! `/sync/<index> <value> <timetag> <instance ID>`, and incoming sync messages are applied unless
+//! the channel has been edited more recently. Simultaneous edits are resolved by keeping the latest
+//! one, with the higher instance ID winning ties, so all instances converge on the same value.
+//! This relies on the machines' clocks being reasonably in sync.
+
+use nannou_osc as osc;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Mutex;
+
+use crate::{timetag, NUM_CHANNELS};
+
+/// The address prefix for sync messages. These don't use the namespace.
+pub const SYNC_PREFIX: &str = "/sync/";
+
+pub struct SyncState {
+    /// Identifies this instance's messages. Picked at random.
+    instance_id: i32,
+    /// The most recent edit to each channel, local or remote.
+    edits: Mutex<Vec<Option<Edit>>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Edit {
+    /// An NTP timestamp, which compares correctly as a single integer.
+    time: u64,
+    instance_id: i32,
+    value: f32,
+}
+
+impl Default for SyncState {
+    fn default() -> Self {
+        Self {
+            instance_id: RandomState::new().build_hasher().finish() as i32,
+            edits: Mutex::new(vec![None; NUM_CHANNELS]),
+        }
+    }
+}
+
+impl SyncState {
+    /// The sync message for a channel that's about to be sent, or `None` if that value was just
+    /// received from a peer and sending it back would only bounce it around.
+    pub fn local_change(&self, index: usize, value: f32) -> Option<(String, Vec<osc::Type>)> {
+        let mut edits = self.edits.lock().unwrap();
+        if let Some(edit) = edits[index] {
+            if edit.instance_id != self.instance_id && edit.value == value {
+                return None;
+            }
+        }
+
+        let timetag = timetag::now();
+        edits[index] = Some(Edit {
+            time: ntp_time(&timetag)?,
+            instance_id: self.instance_id,
+            value,
+        });
+
+        Some((
+            format!("{SYNC_PREFIX}{index}"),
+            vec![
+                osc::Type::Float(value),
+                timetag,
+                osc::Type::Int(self.instance_id),
+            ],
+        ))
+    }
+
+    /// Handle a sync message. `suffix` is the part of the address after [`SYNC_PREFIX`]. Returns
+    /// the channel and its new normalized value if the change wins over the channel's last edit.
+    pub fn receive(&self, suffix: &str, args: &[osc::Type]) -> Option<(usize, f32)> {
+        let index: usize = suffix.parse().ok().filter(|&index| index < NUM_CHANNELS)?;
+        let edit = match args {
+            [osc::Type::Float(value), timetag, osc::Type::Int(instance_id), ..] => Edit {
+                time: ntp_time(timetag)?,
+                instance_id: *instance_id,
+                value: *value,
+            },
+            _ => return None,
+        };
+        if edit.instance_id == self.instance_id || !edit.value.is_finite() {
+            return None;
+        }
+
+        let mut edits = self.edits.lock().unwrap();
+        let wins = match edits[index] {
+            Some(last) => (edit.time, edit.instance_id) > (last.time, last.instance_id),
+            None => true,
+        };
+        if wins {
+            edits[index] = Some(edit);
+            Some((index, edit.value.clamp(0.0, 1.0)))
+        } else {
+            None
+        }
+    }
+}
+
+fn ntp_time(timetag: &osc::Type) -> Option<u64> {
+    match *timetag {
+        osc::Type::Time(seconds, fraction) => Some(((seconds as u64) << 32) | fraction as u64),
+        _ => None,
+    }
+}
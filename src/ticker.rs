@@ -0,0 +1,130 @@
+//! Sends channel updates from a dedicated thread at a fixed tick rate instead of from the audio
+//! thread, so the timing of the network traffic doesn't depend on the host's buffer size. The audio
+//! thread only stores the channels' latest values in an [`OutputSnapshot`], and every tick sends
+//! whatever changed since the previous one as a single frame.
+
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::dirty::DirtyFlags;
+use crate::{BackgroundTask, FrameSource, SpaceRadioParams};
+
+/// How often the thread checks whether ticking has been enabled.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The task executor, shared with the ticker once the wrapper asks for it. Tasks that panic are
+/// caught, just like on the wrapper's own thread.
+pub(crate) type Executor = Arc<dyn Fn(BackgroundTask) + Send + Sync>;
+
+/// The latest output value of every channel, written by the audio thread without locking.
+pub struct OutputSnapshot {
+    values: Vec<AtomicU32>,
+    changed: DirtyFlags,
+}
+
+impl OutputSnapshot {
+    pub fn new(num_channels: usize) -> Self {
+        Self {
+            values: (0..num_channels).map(|_| AtomicU32::new(0)).collect(),
+            changed: DirtyFlags::new(num_channels),
+        }
+    }
+
+    pub fn store(&self, index: usize, value: f32) {
+        self.values[index].store(value.to_bits(), Ordering::Relaxed);
+        self.changed.mark(index);
+    }
+
+    /// Call `f` with every channel that changed since the last call and its latest value.
+    pub fn drain(&self, mut f: impl FnMut(usize, f32)) {
+        self.changed.drain(|index| {
+            let value = f32::from_bits(self.values[index].load(Ordering::Relaxed));
+            f(index, value);
+        });
+    }
+}
+
+/// Stops and joins its thread when dropped.
+pub struct SendTicker {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl SendTicker {
+    pub(crate) fn spawn(
+        params: Arc<SpaceRadioParams>,
+        snapshot: Arc<OutputSnapshot>,
+        executor: Arc<Mutex<Option<Executor>>>,
+    ) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = {
+            let stop = Arc::clone(&stop);
+            thread::Builder::new()
+                .name(String::from("space-radio-sender"))
+                .spawn(move || run(&params, &snapshot, &executor, &stop))
+                .ok()
+        };
+
+        Self { stop, handle }
+    }
+}
+
+impl Drop for SendTicker {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn run(
+    params: &SpaceRadioParams,
+    snapshot: &OutputSnapshot,
+    executor: &Mutex<Option<Executor>>,
+    stop: &AtomicBool,
+) {
+    let mut next_tick = Instant::now();
+    let mut frame = 0u32;
+    while !stop.load(Ordering::Relaxed) {
+        let interval_ms = *params.send_tick_ms.read().unwrap();
+        if interval_ms == 0 {
+            thread::sleep(POLL_INTERVAL);
+            next_tick = Instant::now();
+        } else {
+            // Ticks are scheduled from the previous tick rather than from when it finished so the
+            // rate doesn't drift, but a tick that's overdue doesn't cause a burst to catch up
+            next_tick += Duration::from_millis(interval_ms as u64);
+            let now = Instant::now();
+            if next_tick > now {
+                thread::sleep(next_tick - now);
+            } else {
+                next_tick = now;
+            }
+        }
+
+        // This also sends what was left over when ticking was disabled
+        let Some(execute) = executor.lock().unwrap().clone() else {
+            continue;
+        };
+        let mut sent = false;
+        snapshot.drain(|index, value| {
+            execute(BackgroundTask::UpdateParameter {
+                index,
+                value,
+                frame,
+                source: FrameSource::Tick,
+            });
+            sent = true;
+        });
+        if sent {
+            execute(BackgroundTask::EndFrame {
+                time: SystemTime::now(),
+                source: FrameSource::Tick,
+            });
+            frame = frame.wrapping_add(1);
+        }
+    }
+}
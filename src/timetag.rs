@@ -0,0 +1,94 @@
+//! OSC timetags are NTP timestamps: seconds since 1900 in the upper 32 bits and a binary fraction
+//! of a second in the lower 32 bits.
+
+use nannou_osc as osc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Seconds between the NTP epoch (1900) and the Unix epoch (1970).
+const NTP_UNIX_OFFSET: u64 = 2_208_988_800;
+/// How far the audio clock may drift from the system clock before [`BlockClock`] starts over. This
+/// needs to be larger than the host's buffers, since those are processed in bursts.
+const MAX_CLOCK_DRIFT: Duration = Duration::from_millis(100);
+
+pub fn now() -> osc::Type {
+    from_system_time(SystemTime::now())
+}
+
+pub fn from_system_time(time: SystemTime) -> osc::Type {
+    let since_unix_epoch = time.duration_since(UNIX_EPOCH).unwrap_or(Duration::ZERO);
+    let seconds = since_unix_epoch.as_secs() + NTP_UNIX_OFFSET;
+    let fraction = ((since_unix_epoch.subsec_nanos() as u64) << 32) / 1_000_000_000;
+
+    osc::Type::Time(seconds as u32, fraction as u32)
+}
+
+/// Maps the audio thread's sample position to wall-clock time, so every block gets a timetag that
+/// follows the audio clock instead of the jitter in when the host happens to call `process()`.
+#[derive(Debug, Default)]
+pub struct BlockClock {
+    /// A wall-clock time and the sample position it corresponds to.
+    anchor: Option<(SystemTime, u64)>,
+}
+
+impl BlockClock {
+    pub fn reset(&mut self) {
+        self.anchor = None;
+    }
+
+    /// The wall-clock time of the block starting at `sample_position`.
+    pub fn block_start(&mut self, sample_position: u64, sample_rate: f32) -> SystemTime {
+        let now = SystemTime::now();
+        if let Some((anchor_time, anchor_position)) = self.anchor {
+            let elapsed =
+                sample_position.saturating_sub(anchor_position) as f64 / sample_rate as f64;
+            let expected = anchor_time + Duration::from_secs_f64(elapsed);
+            let drift = match expected.duration_since(now) {
+                Ok(drift) => drift,
+                Err(err) => err.duration(),
+            };
+            if drift <= MAX_CLOCK_DRIFT {
+                return expected;
+            }
+        }
+
+        // Dropouts, sample rate changes, and clock drift all end up here
+        self.anchor = Some((now, sample_position));
+        now
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_unix_time_to_ntp() {
+        assert_eq!(
+            from_system_time(UNIX_EPOCH),
+            osc::Type::Time(NTP_UNIX_OFFSET as u32, 0)
+        );
+        assert_eq!(
+            from_system_time(UNIX_EPOCH + Duration::from_millis(1500)),
+            osc::Type::Time(NTP_UNIX_OFFSET as u32 + 1, 1 << 31)
+        );
+    }
+
+    #[test]
+    fn times_before_the_unix_epoch_are_clamped() {
+        assert_eq!(
+            from_system_time(UNIX_EPOCH - Duration::from_secs(1)),
+            osc::Type::Time(NTP_UNIX_OFFSET as u32, 0)
+        );
+    }
+
+    #[test]
+    fn blocks_follow_the_audio_clock() {
+        let mut clock = BlockClock::default();
+        let first = clock.block_start(0, 48_000.0);
+        let second = clock.block_start(480, 48_000.0);
+        assert_eq!(
+            second.duration_since(first).unwrap(),
+            Duration::from_millis(10)
+        );
+    }
+}
@@ -0,0 +1,131 @@
+//! Generates a TouchOSC layout with a fader for every channel in use, so a tablet can control the
+//! plugin through the receive port without building the layout by hand. Every group gets its own
+//! pages, and the faders send the channel's own address in the range the channel sends in, which
+//! is what the receiver expects.
+//!
+//! A `.touchosc` file is a zip archive with the layout in `index.xml`. TouchOSC expects names,
+//! labels, and addresses in that file to be base64 encoded.
+
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+use crate::{channel_groups, ChannelConfig, SpaceRadioParams};
+
+/// The size of a page in TouchOSC's iPad layout mode, in landscape orientation.
+const PAGE_WIDTH: u32 = 1024;
+const PAGE_HEIGHT: u32 = 768;
+/// How many faders fit on a page before the group continues on another page.
+const FADERS_PER_PAGE: usize = 16;
+/// The height of the name label below each fader.
+const LABEL_HEIGHT: u32 = 40;
+const MARGIN: u32 = 4;
+/// Each page's faders use the next color, so groups are easy to tell apart.
+const COLORS: [&str; 6] = ["yellow", "red", "green", "blue", "purple", "orange"];
+
+/// Write the layout for the current channel configuration to `path`.
+pub(crate) fn export(params: &SpaceRadioParams, path: &Path) -> Result<(), String> {
+    let write = || -> zip::result::ZipResult<()> {
+        let mut archive = zip::ZipWriter::new(File::create(path)?);
+        archive.start_file("index.xml", zip::write::FileOptions::default())?;
+        archive.write_all(layout(params).as_bytes())?;
+        archive.finish()?;
+
+        Ok(())
+    };
+
+    write().map_err(|err| format!("Could not write {}: {err}", path.display()))
+}
+
+fn layout(params: &SpaceRadioParams) -> String {
+    let profile = params.active_profile();
+    let channels = params.channels.read().unwrap();
+    let default_channel = ChannelConfig::default();
+    let namespace = params.osc_namespace.read().unwrap();
+    let namespace = namespace.trim_end_matches('/');
+    let groups = channel_groups(&channels, params.channel_count());
+
+    let column_width = PAGE_WIDTH / FADERS_PER_PAGE as u32;
+    let fader_height = PAGE_HEIGHT - LABEL_HEIGHT - 3 * MARGIN;
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+         <layout version=\"16\" mode=\"1\" orientation=\"horizontal\">",
+    );
+    let pages = groups.iter().flat_map(|(group, members)| {
+        let group = group.unwrap_or("Channels");
+        let page_count = members.chunks(FADERS_PER_PAGE).len();
+        members
+            .chunks(FADERS_PER_PAGE)
+            .enumerate()
+            .map(move |(page, members)| match page_count {
+                1 => (group.to_string(), members),
+                _ => (format!("{group} {}", page + 1), members),
+            })
+    });
+    for (page, (name, members)) in pages.enumerate() {
+        let color = COLORS[page % COLORS.len()];
+        xml += &format!(
+            "<tabpage name=\"{}\" scalef=\"0.0\" scalet=\"1.0\">",
+            base64(&name)
+        );
+        for (column, &index) in members.iter().enumerate() {
+            let channel = channels.get(index).unwrap_or(&default_channel);
+            let address = format!(
+                "{namespace}{}",
+                params.channel_address(index, &channels, &profile)
+            );
+            let label = channel
+                .name
+                .clone()
+                .unwrap_or_else(|| format!("Ch. {}", index + 1));
+            let (min, max) = channel.output_range(&profile);
+            let x = column as u32 * column_width + MARGIN;
+            let width = column_width - 2 * MARGIN;
+
+            xml += &format!(
+                "<control name=\"{}\" x=\"{x}\" y=\"{MARGIN}\" w=\"{width}\" h=\"{fader_height}\" \
+                 color=\"{color}\" type=\"faderv\" scalef=\"{min}\" scalet=\"{max}\" \
+                 osc_cs=\"{}\" response=\"absolute\" inverted=\"false\" centered=\"false\">\
+                 </control>",
+                base64(&format!("fader{}", index + 1)),
+                base64(&address),
+            );
+            xml += &format!(
+                "<control name=\"{}\" x=\"{x}\" y=\"{}\" w=\"{width}\" h=\"{LABEL_HEIGHT}\" \
+                 color=\"gray\" type=\"labelh\" text=\"{}\" size=\"14\" background=\"true\" \
+                 outline=\"false\"></control>",
+                base64(&format!("label{}", index + 1)),
+                fader_height + 2 * MARGIN,
+                base64(&label),
+            );
+        }
+        xml += "</tabpage>";
+    }
+    xml += "</layout>";
+
+    xml
+}
+
+/// Standard base64 with padding, which is all TouchOSC needs and not worth a dependency.
+fn base64(text: &str) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::with_capacity(text.len().div_ceil(3) * 4);
+    for chunk in text.as_bytes().chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let bits = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for position in 0..4 {
+            if position <= chunk.len() {
+                encoded.push(ALPHABET[((bits >> (18 - 6 * position)) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
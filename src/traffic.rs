@@ -0,0 +1,121 @@
+//! A log of the most recent OSC messages for the editor's traffic console, replacing the need to
+//! run Wireshark or oscdump during setup. Messages are only logged while the console is being
+//! looked at, so formatting them doesn't cost anything the rest of the time.
+
+use nannou_osc as osc;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// The most messages the log holds before the oldest ones are dropped.
+const MAX_ENTRIES: usize = 500;
+/// Messages stop being logged once the console hasn't been drawn for this long.
+const WATCH_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    /// When the message was logged, relative to when the log was created.
+    pub time: Duration,
+    pub direction: Direction,
+    pub text: String,
+}
+
+pub struct TrafficLog {
+    created: Instant,
+    entries: Mutex<VecDeque<Entry>>,
+    /// When the console was last drawn.
+    watched: Mutex<Option<Instant>>,
+}
+
+impl Default for TrafficLog {
+    fn default() -> Self {
+        Self {
+            created: Instant::now(),
+            entries: Mutex::new(VecDeque::with_capacity(MAX_ENTRIES)),
+            watched: Mutex::new(None),
+        }
+    }
+}
+
+impl TrafficLog {
+    /// Called by the console whenever it's drawn.
+    pub fn watch(&self) {
+        *self.watched.lock().unwrap() = Some(Instant::now());
+    }
+
+    fn is_watched(&self) -> bool {
+        self.watched
+            .lock()
+            .unwrap()
+            .is_some_and(|watched| watched.elapsed() < WATCH_TIMEOUT)
+    }
+
+    /// Log every message in a packet, including those in nested bundles.
+    pub fn record_packet(&self, direction: Direction, packet: &osc::Packet) {
+        if !self.is_watched() {
+            return;
+        }
+
+        match packet {
+            osc::Packet::Message(message) => self.push(direction, message),
+            osc::Packet::Bundle(bundle) => {
+                for packet in &bundle.content {
+                    self.record_packet(direction, packet);
+                }
+            }
+        }
+    }
+
+    pub fn record_messages(&self, direction: Direction, messages: &[osc::Message]) {
+        if !self.is_watched() {
+            return;
+        }
+
+        for message in messages {
+            self.push(direction, message);
+        }
+    }
+
+    pub fn entries(&self) -> Vec<Entry> {
+        self.entries.lock().unwrap().iter().cloned().collect()
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+
+    fn push(&self, direction: Direction, message: &osc::Message) {
+        let args: Vec<String> = message.args.iter().map(format_arg).collect();
+        let entry = Entry {
+            time: self.created.elapsed(),
+            direction,
+            text: format!("{} {}", message.addr, args.join(" ")),
+        };
+
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= MAX_ENTRIES {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+}
+
+/// A compact representation of an argument for displaying it in the editor.
+pub fn format_arg(arg: &osc::Type) -> String {
+    match arg {
+        osc::Type::Float(value) => format!("{value:.3}"),
+        osc::Type::Double(value) => format!("{value:.3}"),
+        osc::Type::Int(value) => value.to_string(),
+        osc::Type::Long(value) => value.to_string(),
+        osc::Type::Bool(value) => value.to_string(),
+        osc::Type::String(value) => format!("{value:?}"),
+        osc::Type::Blob(bytes) => format!("<{} bytes>", bytes.len()),
+        arg => format!("{arg:?}"),
+    }
+}
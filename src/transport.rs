@@ -0,0 +1,273 @@
+//! Broadcasts the host's transport so visuals can stay locked to the DAW's timeline.
+//!
+//! - `/transport/playing <0 or 1>` whenever playback starts or stops.
+//! - `/transport/position_seconds <seconds>`
+//! - `/transport/bar_beat <bar> <beat>`, both counting from one like DAWs display them. The beat
+//!   includes the fraction of the beat that has passed, and is counted in the time signature's
+//!   note value.
+//! - `/timesig <numerator> <denominator>` whenever the time signature changes.
+//!
+//! The position is sent at the configured interval while playing, and whenever it changes while
+//! stopped, e.g. when the playhead is moved. Hosts that don't report part of the position don't
+//! get those messages.
+//!
+//! The tempo is sent separately as `/tempo <bpm>` whenever it changes, and optionally at a steady
+//! interval so receivers that join later pick it up.
+//!
+//! For tempo-locked visuals, `/phase <phase>` streams a ramp from 0 to 1 over every bar, or over a
+//! configurable number of beats, while the transport is playing.
+
+use nannou_osc as osc;
+use nih_plug::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// The parts of the host's [`Transport`] that get broadcast. This is copied out of the transport
+/// so the process context isn't borrowed while messages are being queued.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TransportInfo {
+    pub playing: bool,
+    pub pos_seconds: Option<f64>,
+    /// The bar and beat, counting from one.
+    pub bar_beat: Option<(i32, f64)>,
+    pub time_sig: Option<(i32, i32)>,
+}
+
+impl TransportInfo {
+    pub fn new(transport: &Transport) -> Self {
+        // Positions in beats are in quarter notes
+        let note_value = transport.time_sig_denominator.unwrap_or(4) as f64;
+        let bar_beat = match (
+            transport.bar_number(),
+            transport.bar_start_pos_beats(),
+            transport.pos_beats(),
+        ) {
+            (Some(bar), Some(bar_start), Some(pos_beats)) => {
+                Some((bar + 1, (pos_beats - bar_start) * note_value / 4.0 + 1.0))
+            }
+            _ => None,
+        };
+
+        Self {
+            playing: transport.playing,
+            pos_seconds: transport.pos_seconds(),
+            bar_beat,
+            time_sig: transport
+                .time_sig_numerator
+                .zip(transport.time_sig_denominator),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransportMessage {
+    Playing(bool),
+    Position { seconds: f64 },
+    BarBeat { bar: i32, beat: f64 },
+    TimeSignature { numerator: i32, denominator: i32 },
+    Tempo(f64),
+    Phase(f64),
+}
+
+impl TransportMessage {
+    /// The OSC address, without the namespace, and arguments for this message.
+    pub fn to_osc(self) -> (String, Vec<osc::Type>) {
+        match self {
+            TransportMessage::Playing(playing) => (
+                String::from("/transport/playing"),
+                vec![osc::Type::Int(playing as i32)],
+            ),
+            TransportMessage::Position { seconds } => (
+                String::from("/transport/position_seconds"),
+                vec![osc::Type::Float(seconds as f32)],
+            ),
+            TransportMessage::BarBeat { bar, beat } => (
+                String::from("/transport/bar_beat"),
+                vec![osc::Type::Int(bar), osc::Type::Float(beat as f32)],
+            ),
+            TransportMessage::TimeSignature {
+                numerator,
+                denominator,
+            } => (
+                String::from("/timesig"),
+                vec![osc::Type::Int(numerator), osc::Type::Int(denominator)],
+            ),
+            TransportMessage::Tempo(tempo) => {
+                (String::from("/tempo"), vec![osc::Type::Float(tempo as f32)])
+            }
+            TransportMessage::Phase(phase) => {
+                (String::from("/phase"), vec![osc::Type::Float(phase as f32)])
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct TransportBroadcast {
+    /// The transport during the previous block, if any.
+    previous: Option<TransportInfo>,
+    /// Samples since the position was last sent.
+    elapsed: u64,
+}
+
+impl TransportBroadcast {
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Call `send` with the messages for this block. While playing, the position is sent every
+    /// `interval_ms`, or every block if that's zero.
+    pub fn process(
+        &mut self,
+        info: TransportInfo,
+        interval_ms: u32,
+        num_samples: usize,
+        sample_rate: f32,
+        mut send: impl FnMut(TransportMessage),
+    ) {
+        // Receivers rebuild their grids from this, so it's sent before the position
+        if let Some((numerator, denominator)) = info.time_sig {
+            if self.previous.map(|previous| previous.time_sig) != Some(info.time_sig) {
+                send(TransportMessage::TimeSignature {
+                    numerator,
+                    denominator,
+                });
+            }
+        }
+
+        let mut send_position = match self.previous {
+            Some(previous) if previous.playing == info.playing => {
+                !info.playing
+                    && (previous.pos_seconds != info.pos_seconds
+                        || previous.bar_beat != info.bar_beat)
+            }
+            _ => {
+                send(TransportMessage::Playing(info.playing));
+                self.elapsed = 0;
+                true
+            }
+        };
+
+        if info.playing {
+            let interval_samples = (interval_ms as f32 / 1000.0 * sample_rate) as u64;
+            self.elapsed += num_samples as u64;
+            if self.elapsed >= interval_samples {
+                send_position = true;
+            }
+        }
+
+        if send_position {
+            if let Some(seconds) = info.pos_seconds {
+                send(TransportMessage::Position { seconds });
+            }
+            if let Some((bar, beat)) = info.bar_beat {
+                send(TransportMessage::BarBeat { bar, beat });
+            }
+            self.elapsed = 0;
+        }
+        self.previous = Some(info);
+    }
+}
+
+#[derive(Default)]
+pub struct TempoBroadcast {
+    last_sent: Option<f64>,
+    /// Samples since the tempo was last sent.
+    elapsed: u64,
+}
+
+impl TempoBroadcast {
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Call `send` if the tempo changed, or if it hasn't been sent for `interval_ms`. An interval
+    /// of zero only sends changes.
+    pub fn process(
+        &mut self,
+        tempo: Option<f64>,
+        interval_ms: u32,
+        num_samples: usize,
+        sample_rate: f32,
+        mut send: impl FnMut(TransportMessage),
+    ) {
+        let Some(tempo) = tempo else {
+            return;
+        };
+
+        let interval_samples = (interval_ms as f32 / 1000.0 * sample_rate) as u64;
+        self.elapsed += num_samples as u64;
+        if self.last_sent != Some(tempo) || (interval_ms > 0 && self.elapsed >= interval_samples) {
+            send(TransportMessage::Tempo(tempo));
+            self.last_sent = Some(tempo);
+            self.elapsed = 0;
+        }
+    }
+}
+
+/// What one ramp of the `/phase` message spans.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum PhaseLength {
+    /// One bar in the host's time signature.
+    #[default]
+    Bar,
+    /// A number of quarter note beats.
+    Beats(f64),
+}
+
+impl PhaseLength {
+    /// The phase within the current ramp, if the host reports enough of its position.
+    pub fn phase(self, transport: &Transport) -> Option<f64> {
+        let pos_beats = transport.pos_beats()?;
+        let (start, length) = match self {
+            PhaseLength::Bar => {
+                let numerator = transport.time_sig_numerator? as f64;
+                let denominator = transport.time_sig_denominator? as f64;
+                (
+                    transport.bar_start_pos_beats()?,
+                    numerator * 4.0 / denominator,
+                )
+            }
+            PhaseLength::Beats(beats) if beats > 0.0 => (0.0, beats),
+            PhaseLength::Beats(_) => return None,
+        };
+
+        Some(((pos_beats - start) / length).rem_euclid(1.0))
+    }
+}
+
+#[derive(Default)]
+pub struct PhaseStream {
+    /// Samples since the phase was last sent.
+    elapsed: u64,
+}
+
+impl PhaseStream {
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Call `send` with the phase every `interval_ms` while the transport is playing, or every
+    /// block if that's zero.
+    pub fn process(
+        &mut self,
+        phase: Option<f64>,
+        playing: bool,
+        interval_ms: u32,
+        num_samples: usize,
+        sample_rate: f32,
+        mut send: impl FnMut(TransportMessage),
+    ) {
+        let (true, Some(phase)) = (playing, phase) else {
+            self.reset();
+            return;
+        };
+
+        // The first block after starting always sends
+        let interval_samples = (interval_ms as f32 / 1000.0 * sample_rate) as u64;
+        if self.elapsed == 0 || self.elapsed >= interval_samples {
+            send(TransportMessage::Phase(phase));
+            self.elapsed = 0;
+        }
+        self.elapsed += num_samples as u64;
+    }
+}
@@ -0,0 +1,153 @@
+//! Keeps an eye on the background task executor. nih-plug owns the thread the tasks run on, so we
+//! can't respawn that thread itself, but we can notice when queued tasks stop being handled and
+//! rebuild the socket those tasks send through. That covers the common failure modes: a send that
+//! panicked while holding the sender's lock, or a socket that got wedged by the OS. Sends can't
+//! hang indefinitely since every socket has a write timeout, and tasks that panic are caught by
+//! the executor, so the thread itself keeps running.
+
+use nih_plug::prelude::*;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, TryLockError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::socket::OscSocket;
+use crate::SpaceRadioParams;
+
+/// How often the watchdog checks on the executor.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Counters shared between the audio thread, which queues tasks, and the task executor, which
+/// handles them. Updating them is wait-free. The host's queue may drop tasks when it's full, so
+/// `queued` can end up ahead of `handled` for good. That's why stalls are detected by `handled` not
+/// moving while new tasks keep being queued, rather than by the difference between the two.
+#[derive(Default)]
+pub struct Heartbeat {
+    queued: AtomicU64,
+    handled: AtomicU64,
+}
+
+impl Heartbeat {
+    pub fn task_queued(&self) {
+        self.queued.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn task_handled(&self) {
+        self.handled.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Forget about the tasks that are still outstanding, for after the socket has been rebuilt.
+    fn reset(&self) {
+        self.queued
+            .store(self.handled.load(Ordering::Relaxed), Ordering::Relaxed);
+    }
+}
+
+/// Stops and joins its thread when dropped.
+pub struct Watchdog {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Watchdog {
+    pub(crate) fn spawn(
+        params: Arc<SpaceRadioParams>,
+        heartbeat: Arc<Heartbeat>,
+        sender: Arc<Mutex<Option<OscSocket>>>,
+    ) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = {
+            let stop = Arc::clone(&stop);
+            thread::Builder::new()
+                .name(String::from("space-radio-watchdog"))
+                .spawn(move || run(&params, &heartbeat, &sender, &stop))
+                .ok()
+        };
+
+        Self { stop, handle }
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn run(
+    params: &SpaceRadioParams,
+    heartbeat: &Heartbeat,
+    sender: &Mutex<Option<OscSocket>>,
+    stop: &AtomicBool,
+) {
+    // When the first task that hasn't been handled yet was queued after the executor was last
+    // seen making progress. Tasks the host dropped before then don't count.
+    let mut waiting_since: Option<Instant> = None;
+    let mut last_handled = heartbeat.handled.load(Ordering::Relaxed);
+    let mut last_queued = heartbeat.queued.load(Ordering::Relaxed);
+    let mut restarts = 0u64;
+
+    while !stop.load(Ordering::Relaxed) {
+        thread::sleep(POLL_INTERVAL);
+
+        // There's no socket to rebuild while the plugin is deactivated
+        if !params.active.load(Ordering::Relaxed) {
+            waiting_since = None;
+            continue;
+        }
+
+        // The executor is only stalled if it stopped handling tasks while new ones keep coming in
+        let handled = heartbeat.handled.load(Ordering::Relaxed);
+        let queued = heartbeat.queued.load(Ordering::Relaxed);
+        let queue_advanced = queued != last_queued;
+        last_queued = queued;
+        if handled != last_handled || handled >= queued {
+            last_handled = handled;
+            waiting_since = None;
+            continue;
+        }
+        let Some(since) = waiting_since else {
+            if queue_advanced {
+                waiting_since = Some(Instant::now());
+            }
+            continue;
+        };
+
+        let timeout_ms = *params.watchdog_timeout_ms.read().unwrap();
+        if timeout_ms == 0 || since.elapsed() < Duration::from_millis(timeout_ms as u64) {
+            continue;
+        }
+
+        // A send that's holding the lock will give up once it hits the write timeout, so we'll try
+        // again during the next poll
+        let mut sender = match sender.try_lock() {
+            Ok(sender) => sender,
+            Err(TryLockError::Poisoned(err)) => err.into_inner(),
+            Err(TryLockError::WouldBlock) => continue,
+        };
+
+        restarts += 1;
+        nih_warn!(
+            "Background tasks stalled with {} pending for over {timeout_ms} ms, rebuilding the \
+             OSC socket (restart #{restarts})",
+            queued - handled
+        );
+        // The old socket has to be closed first, or a fixed local port would still be taken
+        drop(sender.take());
+        *sender = OscSocket::bind(&params.local_bind.read().unwrap())
+            .map_err(|err| nih_error!("Could not bind a new OSC socket: {err}"))
+            .ok();
+        drop(sender);
+
+        // Pending tasks aren't replayed, they'll simply go out through the new socket once the
+        // executor picks them up again. Anything that was lost along the way shouldn't count
+        // against the new socket.
+        heartbeat.reset();
+        last_handled = heartbeat.handled.load(Ordering::Relaxed);
+        last_queued = heartbeat.queued.load(Ordering::Relaxed);
+        waiting_since = None;
+    }
+}